@@ -0,0 +1,184 @@
+use std::time::Instant;
+
+use macroquad::prelude::*;
+
+use crate::boundaries::Boundaries;
+use crate::capture::{self, Recorder, RecordingFormat};
+use crate::scripting::Script;
+use crate::settings::{self, Args, Settings};
+use crate::stats::{CsvWriter, Sample};
+use crate::{scene, Board};
+
+const DEFAULT_TICKS: u64 = 1000; // Number of frames simulated, when "--ticks" isn't given
+const DEFAULT_CSV_INTERVAL: u64 = 10; // Number of frames between two rows of the CSV file, when "--csv-interval" isn't given
+const FRAME_TIME: f32 = 1.0 / 60.0; // Simulated time of a frame (in seconds), the same as in a window running at 60 FPS
+
+// Options of the "run" command
+struct BatchOptions {
+    scene: Option<String>, // The loaded scene file, without it an empty game_board is created for the script
+    width: u16,            // Size of the empty game_board
+    height: u16,
+    ticks: u64,                // Number of simulated frames
+    seed: Option<u64>,         // Replaces the seed saved in the scene file
+    rigid_bodies: bool,        // Simulates the connected solids as rigid bodies
+    out: Option<String>,       // Scene file the final game_board is saved into
+    png: Option<String>,       // Image file the final game_board is drawn into
+    scale: u32,                // Size of a cell in the images (in pixels)
+    gif: Option<String>,       // GIF animation recorded during the run
+    frames: Option<String>,    // Directory the PNG sequence is recorded into during the run
+    every: u64,                // Number of frames between two recorded frames of the animation
+    stats: bool,               // Prints the statistics of the final game_board
+    csv: Option<String>,       // CSV file the statistics are written into during the run
+    csv_interval: u64,         // Number of frames between two rows of the CSV file
+    script: Option<String>,    // Rhai script run with the simulation
+    edges: Option<Boundaries>, // Replaces the modes of the edges saved in the scene file
+}
+
+fn parse_options(args: impl Iterator<Item = String>) -> Result<BatchOptions, String> {
+    let mut options = BatchOptions {
+        scene: None,
+        width: Settings::default().width,
+        height: Settings::default().height,
+        ticks: DEFAULT_TICKS,
+        seed: None,
+        rigid_bodies: false,
+        out: None,
+        png: None,
+        scale: 1,
+        gif: None,
+        frames: None,
+        every: 1,
+        stats: false,
+        csv: None,
+        csv_interval: DEFAULT_CSV_INTERVAL,
+        script: None,
+        edges: None,
+    };
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next()? {
+        match arg.as_str() {
+            "--ticks" => options.ticks = args.number()?,
+            "--seed" => options.seed = Some(args.number()?),
+            "--out" => options.out = Some(args.value()?),
+            "--png" => options.png = Some(args.value()?),
+            "--scale" => options.scale = args.number::<u32>()?.clamp(1, 64),
+            "--gif" => options.gif = Some(args.value()?),
+            "--frames" => options.frames = Some(args.value()?),
+            "--every" => options.every = args.number::<u64>()?.max(1),
+            "--rigid-bodies" => options.rigid_bodies = true,
+            "--stats" => options.stats = true,
+            "--csv" => options.csv = Some(args.value()?),
+            "--csv-interval" => options.csv_interval = args.number::<u64>()?.max(1),
+            "--script" => options.script = Some(args.value()?),
+            "--width" => options.width = settings::parse_board_size(&args.value()?)?,
+            "--height" => options.height = settings::parse_board_size(&args.value()?)?,
+            "--edges" => options.edges = Some(Boundaries::parse(&args.value()?)?),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option \"{arg}\"")),
+            _ if options.scene.is_none() => options.scene = Some(arg),
+            _ => return Err(format!("Unexpected argument \"{arg}\"")),
+        }
+    }
+    if options.scene.is_none() && options.script.is_none() {
+        return Err("Missing the scene file".to_string());
+    }
+    Ok(options)
+}
+
+// The "run" command, it loads a scene file, simulates it without a window and saves the final game_board, with the
+// same seed the results are the same on every computer and with any number of threads, a script can set up the
+// game_board instead of the scene file
+pub(crate) fn run(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let options: BatchOptions = parse_options(args)?;
+    let mut game_board: Board = match &options.scene {
+        Some(path) => scene::load(path)?,
+        None => match options.seed {
+            Some(seed) => Board::with_seed(options.width, options.height, seed),
+            None => Board::new(options.width, options.height),
+        },
+    };
+    if let Some(seed) = options.seed {
+        game_board.seed = seed;
+    }
+    if let Some(edges) = options.edges {
+        game_board.boundaries = edges;
+    }
+    let mut script: Option<Script> = match &options.script {
+        Some(path) => {
+            let mut script: Script = Script::load(path, game_board.seed)?;
+            script.setup(&mut game_board)?;
+            Some(script)
+        }
+        None => None,
+    };
+    let mut csv: Option<CsvWriter> = options.csv.as_deref().map(CsvWriter::create).transpose()?;
+    // Every recorder gets a frame after every "every" ticks
+    let frames: u32 = (options.ticks / options.every) as u32;
+    let mut recorders: Vec<Recorder> = vec![];
+    if let Some(gif) = &options.gif {
+        recorders.push(Recorder::start(
+            RecordingFormat::Gif,
+            gif,
+            frames,
+            options.scale,
+        )?);
+    }
+    if let Some(directory) = &options.frames {
+        recorders.push(Recorder::start(
+            RecordingFormat::PngSequence,
+            directory,
+            frames,
+            options.scale,
+        )?);
+    }
+    let start = Instant::now();
+    let mut last_row = (Instant::now(), 0);
+    for tick in 1..=options.ticks {
+        game_board.step(FRAME_TIME, options.rigid_bodies);
+        if let Some(script) = &mut script {
+            script.after_step(&mut game_board)?;
+        }
+        // The actions are only recorded by the replays of the window, so they'd pile up here
+        game_board.actions.clear();
+        if tick % options.every == 0 {
+            for recorder in &mut recorders {
+                recorder.record(&game_board)?;
+            }
+        }
+        // The ticks per second of a row are measured since the previous row
+        if let Some(csv) = csv
+            .as_mut()
+            .filter(|_| tick % options.csv_interval == 0 || tick == options.ticks)
+        {
+            let ticks_per_second =
+                (tick - last_row.1) as f32 / last_row.0.elapsed().as_secs_f32().max(f32::EPSILON);
+            csv.write(&Sample::new(&game_board, ticks_per_second))?;
+            last_row = (Instant::now(), tick);
+        }
+    }
+    let elapsed: f32 = start.elapsed().as_secs_f32();
+    if let Some(out) = &options.out {
+        scene::save(&game_board, out)?;
+    }
+    if let Some(png) = &options.png {
+        capture::save_png(&game_board, png, options.scale)?;
+    }
+    if options.stats {
+        print_stats(&game_board, options.ticks, elapsed);
+    }
+    Ok(())
+}
+
+fn print_stats(game_board: &Board, ticks: u64, elapsed: f32) {
+    let sample: Sample = Sample::new(game_board, ticks as f32 / elapsed.max(f32::EPSILON));
+    println!(
+        "Simulated {ticks} ticks in {elapsed:.2} s ({:.1} ticks/s), the game_board is at tick {}",
+        sample.ticks_per_second, sample.tick
+    );
+    println!(
+        "{} moving and {} settled particles, {:.1} °C on average",
+        sample.moving, sample.settled, sample.temperature
+    );
+    for (name, (count, temperature)) in &sample.materials {
+        println!("{name}: {count} particles, {temperature:.1} °C on average");
+    }
+}
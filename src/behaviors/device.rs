@@ -0,0 +1,86 @@
+use ::rand::Rng;
+use macroquad::prelude::*;
+
+use super::MaterialBehavior;
+use crate::chunks::Region;
+use crate::devices::Emission;
+use crate::neighbourhood::{Direction, Neighbourhood};
+use crate::{materials, Particle, Phase};
+
+// Emitters spawn their material into the empty neighbouring cell on their side, on average "rate" particles per
+// second, the new particles start moving away from the emitter
+pub(crate) struct Emitter;
+
+impl MaterialBehavior for Emitter {
+    fn update(&self, region: &mut Region, x: i32, y: i32, frame_time: f32) {
+        let Some(cell) = region.index(x, y) else {
+            return;
+        };
+        // The emitters keep their chunk awake, so they don't stop, when nothing moves around them
+        region.contents.changed = true;
+        let Some(Emission {
+            material,
+            rate,
+            direction,
+        }) = region.contents[cell].0.emits
+        else {
+            return;
+        };
+        if region.rng.gen_range(0.0..1.0) >= rate * frame_time {
+            return;
+        }
+        let (Some(material), Some(target)) = (
+            materials::find(material),
+            region
+                .neighbour(x, y, direction)
+                .and_then(|(nx, ny)| region.index(nx, ny)),
+        ) else {
+            return;
+        };
+        let empty: Particle = region.contents[target];
+        if empty.0.phase != Phase::Void {
+            return;
+        }
+        let (dx, dy) = direction.offset();
+        region.reactions.push((empty.0.name, material.name));
+        region.contents[target] = Particle(
+            material,
+            vec2(dx as f32, dy as f32),
+            false,
+            empty.3,
+            0.0,
+            material.temperature,
+        );
+    }
+}
+
+// Drains destroy the particles, which move next to them, only the solids are left alone
+pub(crate) struct Drain;
+
+impl MaterialBehavior for Drain {
+    fn update(&self, region: &mut Region, x: i32, y: i32, _frame_time: f32) {
+        for direction in Direction::SIDES {
+            let Some(target) = region
+                .neighbour(x, y, direction)
+                .and_then(|(nx, ny)| region.index(nx, ny))
+            else {
+                continue;
+            };
+            let particle: Particle = region.contents[target];
+            if matches!(particle.0.phase, Phase::Void | Phase::Solid) {
+                continue;
+            }
+            region
+                .reactions
+                .push((particle.0.name, materials::solid::VOID.name));
+            region.contents[target] = Particle(
+                materials::solid::VOID,
+                vec2(0.0, 0.0),
+                false,
+                particle.3,
+                0.0,
+                crate::AMBIENT_TEMPERATURE,
+            );
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use ::rand::Rng;
+
+use super::MaterialBehavior;
+use crate::chunks::{Region, MAX_REACH};
+use crate::neighbourhood::{Direction, Neighbourhood};
+use crate::Phase;
+
+// Gases move in a random direction every frame (vertically or horizontally), so they slowly fill the space, the
+// higher the viscosity, the shorter their moves
+pub(crate) struct Gas;
+
+impl MaterialBehavior for Gas {
+    fn update(&self, region: &mut Region, x: i32, y: i32, frame_time: f32) {
+        let Some(cell) = region.index(x, y) else {
+            return;
+        };
+        let height: i32 = region.height as i32;
+        let width: i32 = region.width as i32;
+        let viscosity: f32 = region.contents[cell].0.phase.get_viscosity();
+        // Drifting along the velocity, e.g. in a wind, the drifting gas doesn't move at random
+        if region.contents[cell].2 {
+            let (column, row) = region.drift(x, y, false, frame_time);
+            let (column, row) = region.drift(column, row, true, frame_time);
+            if (column, row) != (x, y) {
+                if let Some(moved) = region.get_mut(column, row) {
+                    moved.2 = false;
+                }
+                region.contents[cell].2 = true;
+                return;
+            }
+        }
+        let orientation: i32 = region.rng.gen_range(-2..2);
+        let mut rnd: i32 = region.rng.gen_range(-height..height);
+        let vertical: f32 = rnd as f32 * (1.0 / viscosity);
+        rnd = region.rng.gen_range(-width..width);
+        let horizontal: f32 = rnd as f32 * (1.0 / viscosity);
+        // Both orientations move to the side given by the horizontal velocity, by the distance of their own velocity
+        let (direction, speed): (Option<Direction>, f32) = match orientation {
+            -1 => (Direction::from_offset(0, rnd.signum()), vertical),
+            1 => (Direction::from_offset(rnd.signum(), 0), horizontal),
+            _ => (None, 0.0),
+        };
+        let reach: i32 = (speed.abs() as i32).min(MAX_REACH);
+        if let Some(direction) = direction {
+            for (nx, ny) in region
+                .ray(x, y, direction)
+                .take((reach - 1).max(0) as usize)
+            {
+                let Some((phase, target_mass)) = region
+                    .get(nx, ny)
+                    .map(|target| (target.0.phase, target.0.mass))
+                else {
+                    break;
+                };
+                let (mass, moving): (f32, bool) =
+                    (region.contents[cell].0.mass, region.contents[cell].2);
+                if phase == Phase::Void && target_mass <= mass && moving {
+                    region.swap((x, y), (nx, ny));
+                    if let Some(moved) = region.get_mut(nx, ny) {
+                        moved.2 = false;
+                    }
+                } else if (target_mass >= mass
+                    && matches!(phase, Phase::Liquid { .. } | Phase::Powder { .. }))
+                    || phase == Phase::Solid
+                {
+                    break;
+                }
+            }
+        }
+        region.contents[cell].2 = true;
+    }
+}
@@ -0,0 +1,102 @@
+use ::rand::Rng;
+
+use super::MaterialBehavior;
+use crate::chunks::{Region, MAX_REACH};
+use crate::neighbourhood::{Direction, Neighbourhood};
+use crate::Phase;
+
+// Liquids fall like the powders, then spread to the sides into the empty cells, the higher the viscosity, the slower
+// they spread
+pub(crate) struct Liquid;
+
+impl MaterialBehavior for Liquid {
+    fn update(&self, region: &mut Region, x: i32, y: i32, frame_time: f32) {
+        let Some(cell) = region.index(x, y) else {
+            return;
+        };
+        let height: i32 = region.height as i32;
+        let width: i32 = region.width as i32;
+        let viscosity: f32 = region.contents[cell].0.phase.get_viscosity();
+        // Falling through the lighter materials below, the liquid stops on the solids and the floor
+        region.contents[cell].1.y += crate::GRAVITY * frame_time;
+        // A force field stronger than the gravity lifts the liquid, then it rises and drifts instead of falling
+        if region.contents[cell].1.y < 0.0 && region.contents[cell].2 {
+            let (column, row) = region.drift(x, y, false, frame_time);
+            let (column, row) = region.drift(column, row, true, frame_time);
+            if let Some(moved) = region.get_mut(column, row) {
+                moved.2 = false;
+            }
+            return;
+        }
+        let mut position: Option<(i32, i32)> = region.contents[cell].2.then_some((x, y));
+        for distance in 0..((region.contents[cell].1.y + 1.0) as i32).min(MAX_REACH) {
+            let (mass, moving): (f32, bool) =
+                (region.contents[cell].0.mass, region.contents[cell].2);
+            match region
+                .get(x, y + distance)
+                .map(|below| (below.0.phase, below.0.mass))
+            {
+                Some((phase, below_mass))
+                    if mass > below_mass && phase != Phase::Solid && moving =>
+                {
+                    region.swap((x, y), (x, y + distance));
+                    if let Some(moved) = region.get_mut(x, y + distance) {
+                        moved.2 = false;
+                    }
+                    position = Some((x, y + distance));
+                }
+                // Only the edge of the game_board is the floor, the rows outside of the region are left alone
+                None if region.resolve(x, y + distance).is_none() => {
+                    region.contents[cell].1.y = (y - (height - 1)).abs() as f32
+                }
+                Some((Phase::Solid, _)) => {
+                    region.contents[cell].1.y = distance as f32;
+                    if let Some(below) = region.get_mut(x, y + distance) {
+                        below.2 = false;
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Drifting to the side along the horizontal velocity, e.g. in a wind, the drifting liquid doesn't spread
+        if let Some((column, row)) = position {
+            let drifted: (i32, i32) = region.drift(column, row, true, frame_time);
+            if drifted != (column, row) {
+                if let Some(moved) = region.get_mut(drifted.0, drifted.1) {
+                    moved.2 = false;
+                }
+                region.contents[cell].2 = true;
+                return;
+            }
+        }
+        // Spreading to a random side
+        let rnd: i32 = region.rng.gen_range(-(2.3 * width as f32) as i32..width);
+        let spread: f32 = rnd as f32 * (1.0 / viscosity);
+        let reach: i32 = (spread.abs() as i32).min(MAX_REACH);
+        // The own cell is tried first, after a fall it holds the particle, which was displaced by the liquid
+        if let Some(direction) = Direction::from_offset(rnd.signum(), 0) {
+            let cells = std::iter::once((x, y)).chain(region.ray(x, y, direction));
+            for (nx, ny) in cells.take(reach.max(0) as usize) {
+                let Some((phase, target_mass)) = region
+                    .get(nx, ny)
+                    .map(|target| (target.0.phase, target.0.mass))
+                else {
+                    break;
+                };
+                let (mass, moving): (f32, bool) =
+                    (region.contents[cell].0.mass, region.contents[cell].2);
+                if phase == Phase::Void && target_mass <= mass && moving {
+                    region.swap((x, y), (nx, ny));
+                    if let Some(moved) = region.get_mut(nx, ny) {
+                        moved.2 = false;
+                    }
+                } else if (target_mass >= mass && matches!(phase, Phase::Powder { .. }))
+                    || phase == Phase::Solid
+                {
+                    break;
+                }
+            }
+        }
+        region.contents[cell].2 = true;
+    }
+}
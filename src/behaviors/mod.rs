@@ -0,0 +1,159 @@
+use ::rand::Rng;
+use macroquad::prelude::*;
+
+use crate::chunks::{Region, MAX_REACH};
+use crate::neighbourhood::{Direction, Neighbourhood};
+use crate::{materials, Particle, Phase};
+
+pub(crate) mod device;
+pub(crate) mod gas;
+pub(crate) mod liquid;
+pub(crate) mod powder;
+
+const DRAG: f32 = 10.0; // Part of the drifting velocity lost per second, so the particles slow down after leaving a force field
+const MIN_DRIFT: f32 = 0.01; // Slower drifting velocities stop (in cells per frame)
+
+// Behaviour of a material in the simulation, every material has one (its "behavior" field), so a new kind of
+// material can be added in its own module without changing the solver. The hooks get the region around the solved
+// chunk (with its random numbers in region.rng) and the column x and row y of the particle, they're called in every
+// frame for every particle of the awake chunks except the void
+pub(crate) trait MaterialBehavior: Sync {
+    // Moves the particle, by default it stays in place (e.g. the solids)
+    fn update(&self, _region: &mut Region, _x: i32, _y: i32, _frame_time: f32) {}
+    // Called with a random neighbouring particle (at the column nx and row ny) after the heat exchange, by default the
+    // absorbent materials soak up the liquids
+    fn on_contact(&self, region: &mut Region, x: i32, y: i32, nx: i32, ny: i32) {
+        region.absorb_liquid(x, y, nx, ny);
+    }
+    // Called after the heat exchange and the contact, by default the wet particles dry out above the boiling point
+    // and change between their wet and dry forms
+    fn on_heat(&self, region: &mut Region, x: i32, y: i32, frame_time: f32) {
+        region.dry(x, y, frame_time);
+    }
+}
+
+// The void, the solids and the plasmas, they don't move by themselves
+pub(crate) struct Inert;
+
+impl MaterialBehavior for Inert {}
+
+impl Region<'_> {
+    // Moves the particle along its horizontal (or vertical) velocity, e.g. the one given by a force field, cell by
+    // cell through the cells, which aren't solid and hold something lighter. The velocity slows down by the drag, the
+    // fraction of a cell is rounded up or down at random, so the slow particles drift too, a blocked particle loses
+    // its velocity. Returns the cell, where the particle ended, the caller marks it as moved
+    pub(crate) fn drift(
+        &mut self,
+        x: i32,
+        y: i32,
+        horizontal: bool,
+        frame_time: f32,
+    ) -> (i32, i32) {
+        let Some(cell) = self.index(x, y) else {
+            return (x, y);
+        };
+        let drag: f32 = (1.0 - DRAG * frame_time).max(0.0);
+        let velocity: &mut f32 = match horizontal {
+            true => &mut self.contents[cell].1.x,
+            false => &mut self.contents[cell].1.y,
+        };
+        if *velocity == 0.0 {
+            return (x, y);
+        }
+        *velocity *= drag;
+        if velocity.abs() < MIN_DRIFT {
+            *velocity = 0.0;
+            return (x, y);
+        }
+        let (speed, sign): (f32, bool) = (velocity.abs(), *velocity > 0.0);
+        let mass: f32 = self.contents[cell].0.mass;
+        let mut distance: i32 = speed as i32;
+        if self.rng.gen_range(0.0..1.0) < speed.fract() {
+            distance += 1;
+        }
+        let direction: Direction = match (horizontal, sign) {
+            (true, true) => Direction::Right,
+            (true, false) => Direction::Left,
+            (false, true) => Direction::Down,
+            (false, false) => Direction::Up,
+        };
+        let (mut cx, mut cy) = (x, y);
+        for _ in 0..distance.min(MAX_REACH) {
+            let next: Option<(i32, i32)> = self.neighbour(cx, cy, direction).filter(|(nx, ny)| {
+                self.get(*nx, *ny).is_some_and(|other| {
+                    other.0.phase == Phase::Void
+                        || (other.0.phase != Phase::Solid && other.0.mass < mass)
+                })
+            });
+            let Some((nx, ny)) = next else {
+                if let Some(blocked) = self.get_mut(cx, cy) {
+                    match horizontal {
+                        true => blocked.1.x = 0.0,
+                        false => blocked.1.y = 0.0,
+                    }
+                }
+                break;
+            };
+            self.swap((cx, cy), (nx, ny));
+            (cx, cy) = (nx, ny);
+        }
+        (cx, cy)
+    }
+    // Absorbing a neighbouring liquid particle, hot liquids (e.g. lava) aren't absorbed
+    pub(crate) fn absorb_liquid(&mut self, x: i32, y: i32, nx: i32, ny: i32) {
+        let (Some(cellpos), Some(neighbourpos)) = (self.index(x, y), self.index(nx, ny)) else {
+            return;
+        };
+        let particle: Particle = self.contents[cellpos];
+        if particle.0.absorbency <= 0.0
+            || particle.4 >= crate::SATURATION
+            || self.rng.gen_range(0.0..1.0) >= crate::ABSORPTION_CHANCE
+        {
+            return;
+        }
+        if matches!(self.contents[neighbourpos].0.phase, Phase::Liquid { .. })
+            && self.contents[neighbourpos].5 < crate::BOILING_POINT
+        {
+            self.contents[cellpos].4 += particle.0.absorbency;
+            self.reactions.push((
+                self.contents[neighbourpos].0.name,
+                materials::solid::VOID.name,
+            ));
+            self.contents[neighbourpos] = Particle(
+                materials::solid::VOID,
+                vec2(0.0, 0.0),
+                false,
+                self.contents[neighbourpos].3,
+                0.0,
+                crate::AMBIENT_TEMPERATURE,
+            );
+        }
+    }
+    // Wet particles dry out above the boiling point, the saturated ones turn into their wet form and the dried out
+    // ones turn back into their dry form. Every form binds half of the saturation, so the forms make a chain (e.g.
+    // sand -> wet sand -> mud) and a particle doesn't jump back right after it changed
+    pub(crate) fn dry(&mut self, x: i32, y: i32, frame_time: f32) {
+        let Some(cellpos) = self.index(x, y) else {
+            return;
+        };
+        if self.contents[cellpos].4 > 0.0 && self.contents[cellpos].5 > crate::BOILING_POINT {
+            self.contents[cellpos].4 =
+                (self.contents[cellpos].4 - crate::DRYING_RATE * frame_time).max(0.0);
+            self.contents.changed = true;
+        }
+        let particle: &Particle = &self.contents[cellpos];
+        let (next_form, bound): (Option<&'static str>, f32) = if particle.4 >= crate::SATURATION {
+            (particle.0.wet_form, crate::SATURATION * 0.5)
+        } else if particle.4 < crate::SATURATION * 0.5 {
+            (particle.0.dry_form, -crate::SATURATION * 0.5)
+        } else {
+            (None, 0.0)
+        };
+        if let Some(material) = next_form.and_then(materials::find) {
+            self.reactions
+                .push((self.contents[cellpos].0.name, material.name));
+            self.contents[cellpos].0 = material;
+            self.contents[cellpos].4 -= bound;
+        }
+    }
+}
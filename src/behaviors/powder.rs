@@ -0,0 +1,191 @@
+use ::rand::Rng;
+
+use super::MaterialBehavior;
+use crate::chunks::{Region, MAX_REACH};
+use crate::neighbourhood::Neighbourhood;
+use crate::{Particle, Phase};
+
+// Powders fall, until they land on something, then slide down to the sides depending on their coarseness, so they
+// build piles
+pub(crate) struct Powder;
+
+impl MaterialBehavior for Powder {
+    fn update(&self, region: &mut Region, x: i32, y: i32, frame_time: f32) {
+        let Some(cell) = region.index(x, y) else {
+            return;
+        };
+        // The particles, which already moved in this frame, are skipped
+        if !region.contents[cell].2 {
+            return;
+        }
+        let (phase, mass, random, moisture) = {
+            let particle: &Particle = &region.contents[cell];
+            (particle.0.phase, particle.0.mass, particle.3, particle.4)
+        };
+        // Gravity simulation, the particle falls cell by cell, until it lands on something
+        region.contents[cell].1.y += crate::GRAVITY * frame_time;
+        // A force field stronger than the gravity lifts the particle, then it rises and drifts instead of falling
+        if region.contents[cell].1.y < 0.0 {
+            let (column, row) = region.drift(x, y, false, frame_time);
+            let (column, row) = region.drift(column, row, true, frame_time);
+            if let Some(moved) = region.get_mut(column, row) {
+                moved.2 = false;
+            }
+            return;
+        }
+        let mut row: i32 = y;
+        for _k in 0..(region.contents[cell].1.y as i32).clamp(1, MAX_REACH) {
+            if !region.is_passable_for(mass, x, row + 1) {
+                if let Some(falling) = region.get_mut(x, row) {
+                    falling.1.y = 0.0;
+                }
+                break;
+            }
+            region.swap((x, row), (x, row + 1));
+            row += 1;
+        }
+        // Drifting to the side along the horizontal velocity, e.g. in a wind
+        let (column, row) = region.drift(x, row, true, frame_time);
+        if (column, row) != (x, y) {
+            // This marks that the particle has moved in this frame
+            if let Some(moved) = region.get_mut(column, row) {
+                moved.2 = false;
+            }
+            return;
+        }
+        // Wet powders stick together, so they slide less than the dry ones
+        let coarseness: f32 = phase.get_coarseness()
+            * (1.0 - crate::WETNESS_COHESION * (moisture / crate::SATURATION).min(1.0));
+        // The fine powders have particles, which don't slide at all (depending on their random number), so they build
+        // steeper piles, the coarse powders can run further to the sides before sliding down, so their piles are flatter
+        if random > coarseness * 4.0 {
+            return;
+        }
+        let reach: i32 = 1 + ((coarseness - 0.25).max(0.0) * 4.0) as i32;
+        // Both sides are equally likely to be tried first, so the piles are symmetric
+        let first_side: i32 = if region.rng.gen_range(0..2) == 0 {
+            -1
+        } else {
+            1
+        };
+        for side in [first_side, -first_side] {
+            for distance in 1..=reach {
+                let column: i32 = x + side * distance;
+                if !region.is_passable_for(mass, column, y) {
+                    break;
+                }
+                if region.is_passable_for(mass, column, y + 1) {
+                    region.swap((x, y), (column, y + 1));
+                    if let Some(moved) = region.get_mut(column, y + 1) {
+                        moved.2 = false;
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Region<'_> {
+    // A powder particle of the mass can move into the cells, which aren't solid and contain something lighter than the
+    // particle (void, liquids, gases or lighter powders)
+    fn is_passable_for(&self, mass: f32, x: i32, y: i32) -> bool {
+        self.get(x, y)
+            .is_some_and(|other| other.0.phase != Phase::Solid && other.0.mass < mass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ::rand::rngs::StdRng;
+    use ::rand::{Rng, SeedableRng};
+
+    use crate::conservation::count_materials;
+    use crate::{materials, Board};
+
+    const WIDTH: u16 = 81; // The sand is dropped onto the middle column
+    const HEIGHT: u16 = 48;
+    const POUR_TICKS: u32 = 400; // Ticks, during which a new sand particle is dropped in every tick
+    const SETTLE_TICKS: u32 = 300; // Ticks after the pouring, so the pile comes to rest
+    const MAX_COLUMN_DIFFERENCE: i32 = 2; // Allowed difference between the columns at the same distance from the middle
+    const MAX_SIDE_DIFFERENCE: i32 = 8; // Allowed difference between the number of the particles of the two halves
+    const FRAME_TIME: f32 = 1.0 / 60.0;
+
+    // An empty game_board, the random numbers of the cells and the simulation come from the seed, so every run is
+    // the same
+    fn empty_board(seed: u64) -> Board {
+        let mut rng: StdRng = StdRng::seed_from_u64(seed);
+        let mut game_board: Board = Board::new(WIDTH, HEIGHT);
+        game_board.seed = seed;
+        for particle in game_board.contents.iter_mut() {
+            particle.3 = rng.gen_range(0.0..1.0);
+        }
+        game_board
+    }
+
+    // Number of the sand particles in every column
+    fn profile(game_board: &Board) -> Vec<i32> {
+        let width: usize = game_board.width as usize;
+        (0..width)
+            .map(|col| {
+                (0..game_board.height as usize)
+                    .filter(|row| {
+                        game_board.contents[row * width + col].0.name
+                            == materials::powder::SAND.name
+                    })
+                    .count() as i32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sand_piles_are_symmetric() {
+        for seed in 0..4 {
+            let mut game_board: Board = empty_board(seed);
+            for _ in 0..POUR_TICKS {
+                game_board.paint_cell(WIDTH as i32 / 2, 0, materials::powder::SAND);
+                game_board.step(FRAME_TIME, false);
+            }
+            for _ in 0..SETTLE_TICKS {
+                game_board.step(FRAME_TIME, false);
+            }
+            let profile: Vec<i32> = profile(&game_board);
+            let middle: usize = WIDTH as usize / 2;
+            for distance in 1..=middle {
+                let (left, right) = (profile[middle - distance], profile[middle + distance]);
+                assert!(
+                    (left - right).abs() <= MAX_COLUMN_DIFFERENCE,
+                    "seed {seed}: {left} and {right} particles {distance} columns from the middle in {profile:?}"
+                );
+            }
+            let left: i32 = profile[..middle].iter().sum();
+            let right: i32 = profile[middle + 1..].iter().sum();
+            assert!(
+                (left - right).abs() <= MAX_SIDE_DIFFERENCE,
+                "seed {seed}: {left} particles on the left and {right} on the right in {profile:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn sand_piles_keep_their_particles() {
+        for seed in 0..4 {
+            let mut game_board: Board = empty_board(seed);
+            // A column of sand in the upper half, it falls and spreads into a pile
+            for row in 0..HEIGHT as i32 / 2 {
+                game_board.paint_cell(WIDTH as i32 / 2, row, materials::powder::SAND);
+            }
+            let counts: BTreeMap<&'static str, i64> = count_materials(&game_board);
+            for tick in 0..SETTLE_TICKS {
+                game_board.step(FRAME_TIME, false);
+                assert_eq!(
+                    count_materials(&game_board),
+                    counts,
+                    "seed {seed}: particles were lost or duplicated at tick {tick}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use macroquad::prelude::*;
+
+use crate::camera::BoardCamera;
+use crate::{materials, Board, Particle, Phase, AMBIENT_TEMPERATURE};
+
+const EDGE_THICKNESS: f32 = 2.0; // Width of the lines marking the wrapping and open edges (in pixels)
+
+// What happens with the particles at an edge of the game_board
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub(crate) enum EdgeMode {
+    #[default]
+    Wall, // The edge stops the particles
+    Wrap, // The particles leaving through the edge come back through the opposite edge
+    Open, // The particles fall out through the edge and are destroyed
+}
+
+impl EdgeMode {
+    const ALL: [EdgeMode; 3] = [EdgeMode::Wall, EdgeMode::Wrap, EdgeMode::Open];
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            EdgeMode::Wall => "Wall",
+            EdgeMode::Wrap => "Wrap",
+            EdgeMode::Open => "Open",
+        }
+    }
+    fn parse(name: &str) -> Result<EdgeMode, String> {
+        EdgeMode::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+            .ok_or(format!(
+                "Unknown edge mode \"{name}\", it can be wall, wrap or open"
+            ))
+    }
+    // The next or the previous mode (change is 1 or -1), used by the buttons of the settings panel
+    pub(crate) fn cycle(self, change: i32) -> EdgeMode {
+        let position: usize = EdgeMode::ALL
+            .iter()
+            .position(|mode| *mode == self)
+            .unwrap_or(0);
+        EdgeMode::ALL[(position as i32 + change).rem_euclid(EdgeMode::ALL.len() as i32) as usize]
+    }
+    fn color(self) -> Option<Color> {
+        match self {
+            EdgeMode::Wall => None,
+            EdgeMode::Wrap => Some(SKYBLUE),
+            EdgeMode::Open => Some(ORANGE),
+        }
+    }
+}
+
+// Modes of the 4 edges of the game_board, every edge is a wall by default
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub(crate) struct Boundaries {
+    pub(crate) top: EdgeMode,
+    pub(crate) right: EdgeMode,
+    pub(crate) bottom: EdgeMode,
+    pub(crate) left: EdgeMode,
+}
+
+impl Boundaries {
+    // Reads one mode for every edge (e.g. "wrap") or 4 modes separated by commas in the order top, right, bottom, left
+    // (e.g. "wall,wrap,open,wrap")
+    pub(crate) fn parse(value: &str) -> Result<Boundaries, String> {
+        let modes: Vec<EdgeMode> = value
+            .split(',')
+            .map(|name| EdgeMode::parse(name.trim()))
+            .collect::<Result<_, _>>()?;
+        match modes[..] {
+            [mode] => Ok(Boundaries::from_array([mode; 4])),
+            [top, right, bottom, left] => Ok(Boundaries::from_array([top, right, bottom, left])),
+            _ => Err(format!(
+                "The edges must be one mode or 4 modes (top, right, bottom, left), not \"{value}\""
+            )),
+        }
+    }
+    // The modes in the order top, right, bottom, left
+    pub(crate) fn to_array(self) -> [EdgeMode; 4] {
+        [self.top, self.right, self.bottom, self.left]
+    }
+    pub(crate) fn from_array([top, right, bottom, left]: [EdgeMode; 4]) -> Boundaries {
+        Boundaries {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+    // The modes saved into the scene files (1 byte per edge), unknown bytes are read as walls
+    pub(crate) fn to_bytes(self) -> [u8; 4] {
+        self.to_array().map(|mode| mode as u8)
+    }
+    pub(crate) fn from_bytes(bytes: [u8; 4]) -> Boundaries {
+        Boundaries::from_array(
+            bytes.map(|byte| *EdgeMode::ALL.get(byte as usize).unwrap_or(&EdgeMode::Wall)),
+        )
+    }
+    // The cell at the column x and row y, the cells behind the wrapping edges are on the opposite side, None behind
+    // the other edges
+    pub(crate) fn resolve(self, x: i32, y: i32, width: i32, height: i32) -> Option<(i32, i32)> {
+        let x: i32 = match x {
+            _ if x < 0 && self.left == EdgeMode::Wrap => x.rem_euclid(width),
+            _ if x >= width && self.right == EdgeMode::Wrap => x.rem_euclid(width),
+            _ if x < 0 || x >= width => return None,
+            _ => x,
+        };
+        let y: i32 = match y {
+            _ if y < 0 && self.top == EdgeMode::Wrap => y.rem_euclid(height),
+            _ if y >= height && self.bottom == EdgeMode::Wrap => y.rem_euclid(height),
+            _ if y < 0 || y >= height => return None,
+            _ => y,
+        };
+        Some((x, y))
+    }
+}
+
+impl Board {
+    // Called after the chunks were solved, the solvers stop the particles at an open edge like at a wall, then the
+    // movable particles (not the void and the solids), which reached the outermost row or column of an open edge, are
+    // destroyed here. The wrapping edges don't need anything, the solvers move the particles through them
+    pub(crate) fn solve_edges(&mut self) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let edges: [(EdgeMode, Vec<usize>); 4] = [
+            (self.boundaries.top, (0..width).collect()),
+            (
+                self.boundaries.right,
+                (0..height).map(|row| row * width + width - 1).collect(),
+            ),
+            (
+                self.boundaries.bottom,
+                (0..width).map(|col| (height - 1) * width + col).collect(),
+            ),
+            (
+                self.boundaries.left,
+                (0..height).map(|row| row * width).collect(),
+            ),
+        ];
+        for (mode, cells) in edges {
+            if mode != EdgeMode::Open {
+                continue;
+            }
+            for index in cells {
+                let particle: Particle = self.contents[index];
+                if matches!(particle.0.phase, Phase::Void | Phase::Solid) {
+                    continue;
+                }
+                self.reactions
+                    .push((particle.0.name, materials::solid::VOID.name));
+                self.contents[index] = Particle(
+                    materials::solid::VOID,
+                    vec2(0.0, 0.0),
+                    false,
+                    particle.3,
+                    0.0,
+                    AMBIENT_TEMPERATURE,
+                );
+                self.wake(index);
+            }
+        }
+    }
+}
+
+// Marks the wrapping (blue) and the open (orange) edges of the game_board, the walls aren't marked
+pub(crate) fn draw_edges(game_board: &Board, camera: &BoardCamera) {
+    let top_left: Vec2 = camera.board_to_screen(Vec2::ZERO);
+    let bottom_right: Vec2 =
+        camera.board_to_screen(vec2(game_board.width as f32, game_board.height as f32));
+    let view: Rect = camera.viewport;
+    let (left, right) = (
+        top_left.x.max(view.left()),
+        bottom_right.x.min(view.right()),
+    );
+    let (top, bottom) = (
+        top_left.y.max(view.top()),
+        bottom_right.y.min(view.bottom()),
+    );
+    let boundaries: Boundaries = game_board.boundaries;
+    let lines: [(EdgeMode, bool, Vec2, Vec2); 4] = [
+        (
+            boundaries.top,
+            top_left.y >= view.top(),
+            vec2(left, top),
+            vec2(right, top),
+        ),
+        (
+            boundaries.right,
+            bottom_right.x <= view.right(),
+            vec2(right, top),
+            vec2(right, bottom),
+        ),
+        (
+            boundaries.bottom,
+            bottom_right.y <= view.bottom(),
+            vec2(left, bottom),
+            vec2(right, bottom),
+        ),
+        (
+            boundaries.left,
+            top_left.x >= view.left(),
+            vec2(left, top),
+            vec2(left, bottom),
+        ),
+    ];
+    // Only the edges inside the viewport are drawn, the others are scrolled out of the view
+    for (mode, visible, start, end) in lines {
+        if let Some(color) = mode.color().filter(|_| visible) {
+            draw_line(start.x, start.y, end.x, end.y, EDGE_THICKNESS, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Material;
+
+    const WIDTH: u16 = 8;
+    const HEIGHT: u16 = 70; // 3 chunk rows, so the first and the last one are solved in the same round
+    const FRAME_TIME: f32 = 1.0 / 60.0;
+
+    // Rows of the particles of the material
+    fn rows_of(game_board: &Board, material: &Material) -> Vec<usize> {
+        let width: usize = game_board.width as usize;
+        (game_board.contents.iter().enumerate())
+            .filter(|(_, particle)| particle.0.name == material.name)
+            .map(|(index, _)| index / width)
+            .collect()
+    }
+
+    // A particle of the material dropped onto the bottom row with the bottom edge in the mode, returns its rows after
+    // every step
+    fn drop_on_bottom(material: Material, bottom: EdgeMode) -> Vec<Vec<usize>> {
+        let mut game_board: Board = Board::new(WIDTH, HEIGHT);
+        game_board.boundaries.bottom = bottom;
+        game_board.paint_cell(3, HEIGHT as i32 - 1, material);
+        (0..10)
+            .map(|_| {
+                game_board.step(FRAME_TIME, false);
+                rows_of(&game_board, &material)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn falling_particles_go_through_a_wrapping_bottom() {
+        for material in [materials::powder::SAND, materials::liquid::WATER] {
+            let rows: Vec<Vec<usize>> = drop_on_bottom(material, EdgeMode::Wrap);
+            assert!(rows.iter().all(|rows| rows.len() == 1), "{rows:?}");
+            assert!(rows.iter().any(|rows| rows[0] < 10), "{rows:?}");
+        }
+    }
+
+    #[test]
+    fn falling_particles_stay_on_a_wall_and_leave_through_an_open_bottom() {
+        for material in [materials::powder::SAND, materials::liquid::WATER] {
+            let rows: Vec<Vec<usize>> = drop_on_bottom(material, EdgeMode::Wall);
+            assert!(
+                rows.iter().all(|rows| rows[..] == [HEIGHT as usize - 1]),
+                "{rows:?}"
+            );
+            let rows: Vec<Vec<usize>> = drop_on_bottom(material, EdgeMode::Open);
+            assert!(rows.iter().all(|rows| rows.is_empty()), "{rows:?}");
+        }
+    }
+}
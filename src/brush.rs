@@ -0,0 +1,341 @@
+use std::collections::HashSet;
+
+use macroquad::prelude::*;
+
+use crate::camera::BoardCamera;
+use crate::forces::ForceField;
+use crate::replay::Action;
+use crate::{draw_button, Board, Material, Particle, BUTTON_HEIGHT, BUTTON_SPACING, BUTTON_WIDTH};
+
+pub(crate) const MAX_BRUSH_RADIUS: i32 = 50;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum BrushShape {
+    Circle,
+    Square,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Tool {
+    Brush,     // Paints under the cursor while the button is held down
+    Line, // Paints a line from the cell, where the button was pressed, to the cell, where it was released
+    Rectangle, // Fills a rectangle between the cells, where the button was pressed and released
+    Fill, // Replaces the connected region of the material under the cursor
+    Field, // Places a force field with the size of the brush, the right button removes the fields under the cursor
+}
+
+pub(crate) struct Brush {
+    pub(crate) tool: Tool,
+    pub(crate) shape: BrushShape,
+    pub(crate) radius: i32,         // 0 paints a single cell
+    last_cell: Option<(i32, i32)>, // Cell under the cursor in the last frame, while painting with the brush
+    drag_start: Option<(i32, i32)>, // Cell, where the line or the rectangle starts
+    pub(crate) field: ForceField,  // Kind and strength of the fields placed with the field tool
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Brush {
+            tool: Tool::Brush,
+            shape: BrushShape::Circle,
+            radius: 0,
+            last_cell: None,
+            drag_start: None,
+            field: ForceField::default(),
+        }
+    }
+}
+
+impl Brush {
+    // Paints with the left and erases with the right mouse button using the selected tool
+    pub(crate) fn handle_input(
+        &mut self,
+        game_board: &mut Board,
+        material: Material,
+        eraser: Material,
+        camera: &BoardCamera,
+    ) {
+        let cell = cursor_cell(game_board, camera);
+        let left = is_mouse_button_down(MouseButton::Left);
+        let right = is_mouse_button_down(MouseButton::Right);
+        let material = if left { material } else { eraser };
+        match self.tool {
+            Tool::Brush => {
+                if let (Some(cell), true) = (cell, left || right) {
+                    // The stroke is interpolated from the last frame, so fast mouse movements don't leave gaps
+                    let from = self.last_cell.unwrap_or(cell);
+                    game_board.paint_line(from, cell, material, self.shape, self.radius);
+                    self.last_cell = Some(cell);
+                } else {
+                    self.last_cell = None;
+                }
+            }
+            Tool::Line | Tool::Rectangle => {
+                if is_mouse_button_pressed(MouseButton::Left)
+                    || is_mouse_button_pressed(MouseButton::Right)
+                {
+                    self.drag_start = cell;
+                    self.last_cell = cell;
+                }
+                if let Some(cell) = cell {
+                    self.last_cell = Some(cell);
+                }
+                let released = is_mouse_button_released(MouseButton::Left)
+                    || is_mouse_button_released(MouseButton::Right);
+                if let (Some(start), Some(end), true) = (self.drag_start, self.last_cell, released)
+                {
+                    let material = if is_mouse_button_released(MouseButton::Left) {
+                        material
+                    } else {
+                        eraser
+                    };
+                    if self.tool == Tool::Line {
+                        game_board.paint_line(start, end, material, self.shape, self.radius);
+                    } else {
+                        game_board.paint_rectangle(start, end, material);
+                    }
+                    self.drag_start = None;
+                }
+            }
+            Tool::Fill => {
+                if let Some((x, y)) = cell {
+                    if is_mouse_button_pressed(MouseButton::Left)
+                        || is_mouse_button_pressed(MouseButton::Right)
+                    {
+                        game_board.flood_fill(x, y, material);
+                    }
+                }
+            }
+            Tool::Field => {
+                if let Some((x, y)) = cell {
+                    if is_mouse_button_pressed(MouseButton::Left) {
+                        game_board.add_field(ForceField {
+                            x,
+                            y,
+                            radius: self.radius,
+                            ..self.field
+                        });
+                    }
+                    if is_mouse_button_pressed(MouseButton::Right) {
+                        game_board.remove_fields(x, y);
+                    }
+                }
+            }
+        }
+    }
+    // Outlines the area, which will be painted
+    pub(crate) fn draw_preview(&self, game_board: &Board, camera: &BoardCamera) {
+        let color = Color::new(1.0, 1.0, 1.0, 0.6);
+        let cell_center =
+            |(x, y): (i32, i32)| camera.board_to_screen(vec2(x as f32 + 0.5, y as f32 + 0.5));
+        match (self.tool, self.drag_start, self.last_cell) {
+            (Tool::Line, Some(start), Some(end)) => {
+                let (start, end) = (cell_center(start), cell_center(end));
+                let thickness = ((self.radius * 2 + 1) as f32 * camera.zoom).max(1.0);
+                draw_line(start.x, start.y, end.x, end.y, thickness, color);
+            }
+            (Tool::Rectangle, Some(start), Some(end)) => {
+                let top_left = camera
+                    .board_to_screen(vec2(start.0.min(end.0) as f32, start.1.min(end.1) as f32));
+                let size = vec2(
+                    ((start.0 - end.0).abs() + 1) as f32,
+                    ((start.1 - end.1).abs() + 1) as f32,
+                ) * camera.zoom;
+                draw_rectangle_lines(top_left.x, top_left.y, size.x, size.y, 2.0, color);
+            }
+            _ => {
+                if let Some(cell) = cursor_cell(game_board, camera) {
+                    let center = cell_center(cell);
+                    let size = (self.radius as f32 + 0.5) * camera.zoom;
+                    match self.shape {
+                        BrushShape::Circle => {
+                            draw_circle_lines(center.x, center.y, size, 1.0, color)
+                        }
+                        BrushShape::Square => draw_rectangle_lines(
+                            center.x - size,
+                            center.y - size,
+                            size * 2.0,
+                            size * 2.0,
+                            1.0,
+                            color,
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Column and row of the cell under the cursor, None if the cursor isn't above the game_board
+fn cursor_cell(game_board: &Board, camera: &BoardCamera) -> Option<(i32, i32)> {
+    let position = camera.screen_to_board(mouse_position().into())?.floor();
+    let (x, y) = (position.x as i32, position.y as i32);
+    (x >= 0 && x < game_board.width as i32 && y >= 0 && y < game_board.height as i32)
+        .then_some((x, y))
+}
+
+// Draws the tool, shape and brush size buttons
+pub(crate) fn draw_brush_panel(brush: &mut Brush, x: f32, y: f32) {
+    let tools = [
+        (Tool::Brush, "Brush"),
+        (Tool::Line, "Line"),
+        (Tool::Rectangle, "Rectangle"),
+        (Tool::Fill, "Fill"),
+        (Tool::Field, "Field"),
+    ];
+    for (row, (tool, label)) in tools.iter().enumerate() {
+        let color = if brush.tool == *tool {
+            DARKBLUE
+        } else {
+            DARKGRAY
+        };
+        if draw_button(
+            label,
+            x,
+            y + BUTTON_SPACING * row as f32,
+            BUTTON_WIDTH,
+            BUTTON_HEIGHT,
+            color,
+        ) {
+            brush.tool = *tool;
+        }
+    }
+    let row_y = y + BUTTON_SPACING * tools.len() as f32;
+    let shape_label = match brush.shape {
+        BrushShape::Circle => "Circle",
+        BrushShape::Square => "Square",
+    };
+    if draw_button(shape_label, x, row_y, BUTTON_WIDTH, BUTTON_HEIGHT, DARKGRAY) {
+        brush.shape = match brush.shape {
+            BrushShape::Circle => BrushShape::Square,
+            BrushShape::Square => BrushShape::Circle,
+        };
+    }
+    let row_y = row_y + BUTTON_SPACING;
+    draw_text(
+        &format!("Size: {}", brush.radius),
+        x,
+        row_y + 20.0,
+        20.0,
+        WHITE,
+    );
+    if draw_button("-", x + 65.0, row_y, 20.0, BUTTON_HEIGHT, DARKGRAY) {
+        brush.radius = (brush.radius - 1).max(0);
+    }
+    if draw_button("+", x + 88.0, row_y, 20.0, BUTTON_HEIGHT, DARKGRAY) {
+        brush.radius = (brush.radius + 1).min(MAX_BRUSH_RADIUS);
+    }
+}
+
+impl Board {
+    // Replaces the particle in the cell with a new particle of the material, the change is recorded for the history. A
+    // cell, which already holds the material, is left as it is, so holding the brush still doesn't pile up edits
+    pub(crate) fn paint_cell(&mut self, x: i32, y: i32, material: Material) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let index = (y * self.width as i32 + x) as usize;
+        if self.contents[index].0.name == material.name {
+            return;
+        }
+        let before = self.contents[index];
+        self.contents[index] = Particle(
+            material,
+            material.phase.initial_velocity(),
+            true,
+            self.contents[index].3,
+            0.0,
+            material.temperature,
+        );
+        self.edits.push((index, before, self.contents[index]));
+        self.wake(index);
+    }
+    // Paints the cells under the brush, which the stroke hasn't covered yet
+    fn paint_stamp(
+        &mut self,
+        (x, y): (i32, i32),
+        material: Material,
+        shape: BrushShape,
+        radius: i32,
+        covered: &mut HashSet<(i32, i32)>,
+    ) {
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if shape == BrushShape::Circle && dx * dx + dy * dy > radius * radius + radius {
+                    continue;
+                }
+                if covered.insert((x + dx, y + dy)) {
+                    self.paint_cell(x + dx, y + dy, material);
+                }
+            }
+        }
+    }
+    // Stamps the brush along the line between the two cells (Bresenham's line algorithm)
+    pub(crate) fn paint_line(
+        &mut self,
+        from: (i32, i32),
+        to: (i32, i32),
+        material: Material,
+        shape: BrushShape,
+        radius: i32,
+    ) {
+        self.actions.push(Action::Line {
+            from,
+            to,
+            material,
+            shape,
+            radius,
+        });
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), -(to.1 - y).abs());
+        let (step_x, step_y) = ((to.0 - x).signum(), (to.1 - y).signum());
+        let mut error = dx + dy;
+        // The stamps of the neighbouring steps mostly overlap, so every cell is painted only once per line
+        let mut covered: HashSet<(i32, i32)> = HashSet::new();
+        loop {
+            self.paint_stamp((x, y), material, shape, radius, &mut covered);
+            if (x, y) == to {
+                break;
+            }
+            // Both steps are decided by the error before the step, otherwise the line can miss its end
+            let doubled_error = 2 * error;
+            if doubled_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled_error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+    pub(crate) fn paint_rectangle(&mut self, from: (i32, i32), to: (i32, i32), material: Material) {
+        self.actions.push(Action::Rectangle { from, to, material });
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            for x in from.0.min(to.0)..=from.0.max(to.0) {
+                self.paint_cell(x, y, material);
+            }
+        }
+    }
+    // Replaces every cell connected to the starting cell, which has the same material as the starting cell
+    pub(crate) fn flood_fill(&mut self, x: i32, y: i32, material: Material) {
+        self.actions.push(Action::Fill { x, y, material });
+        let col_count = self.width as i32;
+        let row_count = self.height as i32;
+        let target = self.contents[(y * col_count + x) as usize].0.name;
+        if target == material.name {
+            return;
+        }
+        let mut stack: Vec<(i32, i32)> = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if x < 0 || y < 0 || x >= col_count || y >= row_count {
+                continue;
+            }
+            if self.contents[(y * col_count + x) as usize].0.name != target {
+                continue;
+            }
+            self.paint_cell(x, y, material);
+            stack.extend([(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use macroquad::prelude::*;
+
+use crate::settings::Settings;
+use crate::{BOARD_X, BOARD_Y};
+
+const MAX_VIEWPORT_WIDTH: f32 = 1200.0; // Bigger boards don't fit on the screen, they're only shown partially
+const MAX_VIEWPORT_HEIGHT: f32 = 800.0;
+const MIN_ZOOM: f32 = 0.25; // Size of a cell on the screen, when zoomed out the most (in pixels)
+const MAX_ZOOM: f32 = 64.0;
+const ZOOM_STEP: f32 = 1.25; // Change of the zoom per mouse wheel step
+
+// The part of the game_board shown on the screen, it can be zoomed with the mouse wheel and moved by dragging with the
+// middle mouse button
+pub(crate) struct BoardCamera {
+    pub(crate) viewport: Rect, // Part of the screen, where the game_board is drawn
+    pub(crate) position: Vec2, // Column and row of the game_board shown in the top left corner of the viewport
+    pub(crate) zoom: f32,      // Size of a cell on the screen (in pixels)
+    default_zoom: f32,         // Zoom restored by pressing the Home key
+    drag_origin: Option<Vec2>, // Position of the cursor in the last frame while dragging
+}
+
+impl BoardCamera {
+    pub(crate) fn new(settings: &Settings) -> BoardCamera {
+        let cell_size = settings.cell_size as f32;
+        BoardCamera {
+            viewport: Rect::new(
+                BOARD_X,
+                BOARD_Y,
+                (settings.width as f32 * cell_size).min(MAX_VIEWPORT_WIDTH),
+                (settings.height as f32 * cell_size).min(MAX_VIEWPORT_HEIGHT),
+            ),
+            position: Vec2::ZERO,
+            zoom: cell_size,
+            default_zoom: cell_size,
+            drag_origin: None,
+        }
+    }
+    // Converts a position on the screen into the position on the game_board (in cells), None if it's outside the
+    // viewport
+    pub(crate) fn screen_to_board(&self, screen: Vec2) -> Option<Vec2> {
+        if !self.viewport.contains(screen) {
+            return None;
+        }
+        Some(self.position + (screen - self.viewport.point()) / self.zoom)
+    }
+    pub(crate) fn board_to_screen(&self, board: Vec2) -> Vec2 {
+        self.viewport.point() + (board - self.position) * self.zoom
+    }
+    // The cells of the game_board visible in the viewport (x, y - first column and row, w, h - number of columns and rows)
+    pub(crate) fn visible_cells(&self, width: u16, height: u16) -> Rect {
+        let start = self.position.max(Vec2::ZERO);
+        let end = (self.position + self.viewport.size() / self.zoom)
+            .min(vec2(width as f32, height as f32));
+        Rect::new(
+            start.x,
+            start.y,
+            (end.x - start.x).max(0.0),
+            (end.y - start.y).max(0.0),
+        )
+    }
+    pub(crate) fn handle_input(&mut self, width: u16, height: u16) {
+        let cursor: Vec2 = mouse_position().into();
+        if is_key_pressed(KeyCode::Home) {
+            self.position = Vec2::ZERO;
+            self.zoom = self.default_zoom;
+        }
+        // Zooming, the cell under the cursor stays in place
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 {
+            if let Some(board_position) = self.screen_to_board(cursor) {
+                let factor = if wheel > 0.0 {
+                    ZOOM_STEP
+                } else {
+                    1.0 / ZOOM_STEP
+                };
+                self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+                self.position = board_position - (cursor - self.viewport.point()) / self.zoom;
+            }
+        }
+        // Panning
+        if is_mouse_button_down(MouseButton::Middle) {
+            if let Some(origin) = self.drag_origin {
+                self.position -= (cursor - origin) / self.zoom;
+            }
+            if self.drag_origin.is_some() || self.viewport.contains(cursor) {
+                self.drag_origin = Some(cursor);
+            }
+        } else {
+            self.drag_origin = None;
+        }
+        // At least half of the viewport always shows the game_board
+        let half_view = self.viewport.size() / self.zoom / 2.0;
+        self.position = self.position.clamp(
+            -half_view,
+            (vec2(width as f32, height as f32) - half_view).max(-half_view),
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops, Delay, Frame, RgbaImage};
+use macroquad::prelude::*;
+
+use crate::{renderer, Board};
+
+const RECORDING_FRAMES: u32 = 300; // Number of frames recorded after pressing the G key
+const GIF_FRAME_DELAY: u32 = 33; // Time between two frames of the GIF animations (in milliseconds)
+const GIF_SPEED: i32 = 10; // Speed of the colour quantization of the GIF frames (1 - best quality, 30 - fastest)
+
+// The colours of the game_board as an image, every cell is a square of scale × scale pixels, the void is drawn black,
+// it doesn't need a window, so it works in the headless mode too
+pub(crate) fn board_image(game_board: &Board, scale: u32) -> RgbaImage {
+    let mut pixels: Vec<u8> = renderer::board_pixels(game_board);
+    // The image is opaque, the transparent colours (e.g. of the void) are blended over black
+    for pixel in pixels.chunks_exact_mut(4) {
+        for channel in 0..3 {
+            pixel[channel] = (pixel[channel] as u16 * pixel[3] as u16 / 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+    let image = RgbaImage::from_raw(game_board.width as u32, game_board.height as u32, pixels)
+        .expect("The game_board has 4 bytes per cell");
+    if scale <= 1 {
+        return image;
+    }
+    imageops::resize(
+        &image,
+        image.width() * scale,
+        image.height() * scale,
+        imageops::FilterType::Nearest,
+    )
+}
+
+pub(crate) fn save_png(game_board: &Board, path: &str, scale: u32) -> Result<(), String> {
+    board_image(game_board, scale)
+        .save(path)
+        .map_err(|error| format!("Couldn't save \"{path}\": {error}"))
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum RecordingFormat {
+    Gif,         // A single animated GIF file
+    PngSequence, // A directory with a PNG file for every frame
+}
+
+// Saves the next frames of the simulation into a GIF animation or a PNG sequence
+pub(crate) struct Recorder {
+    path: String, // The GIF file or the directory of the PNG sequence
+    scale: u32,
+    frames_left: u32,
+    frame: u32,                                   // Number of the frames saved so far
+    encoder: Option<GifEncoder<BufWriter<File>>>, // Only the GIF animations are encoded, the PNG frames are saved at once
+}
+
+impl Recorder {
+    pub(crate) fn start(
+        format: RecordingFormat,
+        path: &str,
+        frames: u32,
+        scale: u32,
+    ) -> Result<Recorder, String> {
+        let encoder = match format {
+            RecordingFormat::Gif => {
+                let file = File::create(path)
+                    .map_err(|error| format!("Couldn't create \"{path}\": {error}"))?;
+                let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), GIF_SPEED);
+                encoder
+                    .set_repeat(Repeat::Infinite)
+                    .map_err(|error| format!("Couldn't write \"{path}\": {error}"))?;
+                Some(encoder)
+            }
+            RecordingFormat::PngSequence => {
+                std::fs::create_dir_all(path)
+                    .map_err(|error| format!("Couldn't create \"{path}\": {error}"))?;
+                None
+            }
+        };
+        Ok(Recorder {
+            path: path.to_string(),
+            scale,
+            frames_left: frames,
+            frame: 0,
+            encoder,
+        })
+    }
+    pub(crate) fn is_finished(&self) -> bool {
+        self.frames_left == 0
+    }
+    // Saves the game_board as the next frame, the GIF file is complete, when the recorder is dropped
+    pub(crate) fn record(&mut self, game_board: &Board) -> Result<(), String> {
+        if self.is_finished() {
+            return Ok(());
+        }
+        let image = board_image(game_board, self.scale);
+        match &mut self.encoder {
+            Some(encoder) => encoder
+                .encode_frame(Frame::from_parts(
+                    image,
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(GIF_FRAME_DELAY, 1),
+                ))
+                .map_err(|error| format!("Couldn't write \"{}\": {error}", self.path))?,
+            None => {
+                let path = format!("{}/frame-{:05}.png", self.path, self.frame);
+                image
+                    .save(&path)
+                    .map_err(|error| format!("Couldn't save \"{path}\": {error}"))?;
+            }
+        }
+        self.frame += 1;
+        self.frames_left -= 1;
+        Ok(())
+    }
+}
+
+// Screenshots and recordings made with the keys: P saves a screenshot, G records a GIF animation and Shift+G a PNG
+// sequence, with Shift the screenshot is scaled up to the cell size instead of one pixel per cell
+#[derive(Default)]
+pub(crate) struct Capture {
+    recorder: Option<Recorder>,
+    message: String, // The last saved file or error
+}
+
+impl Capture {
+    pub(crate) fn handle_input(&mut self, game_board: &Board, cell_size: u32) {
+        let shift: bool = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        // The files are named after the current time, so they don't overwrite each other
+        let time: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        if is_key_pressed(KeyCode::P) {
+            let path = format!("screenshot-{time}.png");
+            let scale = if shift { cell_size } else { 1 };
+            self.message = match save_png(game_board, &path, scale) {
+                Ok(()) => format!("Saved {path}"),
+                Err(error) => error,
+            };
+        }
+        if is_key_pressed(KeyCode::G) {
+            if self.recorder.take().is_some() {
+                self.message = "Recording stopped".to_string();
+                return;
+            }
+            let (format, path) = if shift {
+                (RecordingFormat::PngSequence, format!("recording-{time}"))
+            } else {
+                (RecordingFormat::Gif, format!("recording-{time}.gif"))
+            };
+            match Recorder::start(format, &path, RECORDING_FRAMES, 1) {
+                Ok(recorder) => {
+                    self.recorder = Some(recorder);
+                    self.message = format!("Recording {path}");
+                }
+                Err(error) => self.message = error,
+            }
+        }
+    }
+    // Called after every simulated frame
+    pub(crate) fn record(&mut self, game_board: &Board) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        if let Err(error) = recorder.record(game_board) {
+            self.message = error;
+            self.recorder = None;
+        } else if recorder.is_finished() {
+            self.message = format!("Saved {}", recorder.path);
+            self.recorder = None;
+        }
+    }
+    pub(crate) fn draw(&self, x: f32, y: f32) {
+        let text = match &self.recorder {
+            Some(recorder) => format!("{} ({} frames left)", self.message, recorder.frames_left),
+            None => self.message.clone(),
+        };
+        draw_text(&text, x, y, 20.0, WHITE);
+    }
+}
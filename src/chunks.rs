@@ -0,0 +1,369 @@
+use std::ops::{Index, IndexMut, Range};
+
+use ::rand::rngs::StdRng;
+use ::rand::SeedableRng;
+use rayon::prelude::*;
+
+use crate::boundaries::{Boundaries, EdgeMode};
+use crate::forces::ForceField;
+use crate::{Board, Particle};
+
+pub(crate) const CHUNK_SIZE: usize = 32; // Width and height of a chunk (in cells)
+pub(crate) const MAX_REACH: i32 = CHUNK_SIZE as i32 / 2; // Farthest distance a particle can move or look in a frame (in cells)
+const SLEEP_DELAY: u8 = 10; // Number of frames without any change, after which a chunk falls asleep
+pub(crate) const WAKE_TEMPERATURE: f32 = 0.01; // Smallest change of the temperature in a frame, which keeps a chunk awake (in °C)
+
+type Reaction = (&'static str, &'static str); // Material of a particle before and after a reaction
+
+// Rows of the game_board, which can be changed while a chunk is solved, they're indexed the same way as the contents of
+// the whole game_board
+pub(crate) struct Cells<'a> {
+    first: usize, // Index of the first cell
+    cells: &'a mut [Particle],
+    pub(crate) changed: bool, // Something moved or changed in the solved chunk, so it has to stay awake
+    pub(crate) moves: u64,    // Number of the swaps of the particles, it's counted for the stats
+}
+
+impl Index<usize> for Cells<'_> {
+    type Output = Particle;
+    fn index(&self, index: usize) -> &Particle {
+        &self.cells[index - self.first]
+    }
+}
+
+impl IndexMut<usize> for Cells<'_> {
+    fn index_mut(&mut self, index: usize) -> &mut Particle {
+        &mut self.cells[index - self.first]
+    }
+}
+
+impl<'a> Cells<'a> {
+    // The rows starting at the cell with the index first
+    pub(crate) fn new(first: usize, cells: &'a mut [Particle]) -> Cells<'a> {
+        Cells {
+            first,
+            cells,
+            changed: false,
+            moves: 0,
+        }
+    }
+    pub(crate) fn swap(&mut self, a: usize, b: usize) {
+        if self[a].0.name != self[b].0.name {
+            self.changed = true;
+        }
+        self.moves += 1;
+        self.cells.swap(a - self.first, b - self.first);
+    }
+    pub(crate) fn holds(&self, index: usize) -> bool {
+        (self.first..self.first + self.cells.len()).contains(&index)
+    }
+}
+
+// The part of the game_board around the solved chunk, the particles are moved and heated through it
+pub(crate) struct Region<'a> {
+    pub(crate) width: u16, // Size of the whole game_board
+    pub(crate) height: u16,
+    pub(crate) contents: Cells<'a>,
+    pub(crate) reactions: Vec<Reaction>, // Materials changed in the region
+    pub(crate) rng: StdRng, // Random numbers of the chunk, they don't depend on the order in which the chunks are solved
+    pub(crate) boundaries: Boundaries, // Modes of the edges of the game_board
+}
+
+impl Board {
+    // Advances the simulation by one frame, it doesn't need a window, so it can run without the graphics
+    pub(crate) fn step(&mut self, frame_time: f32, rigid_bodies: bool) {
+        debug_assert_eq!(
+            self.contents.len(),
+            self.width as usize * self.height as usize
+        );
+        self.reactions.clear();
+        self.moves = 0;
+        self.wake_fields();
+        // The particles moved in this frame are marked, so they aren't moved again, when the scan reaches them, nothing
+        // moves in the sleeping chunks, so their marks don't have to be cleared
+        for cells in self.awake_rows() {
+            for particle in &mut self.contents[cells] {
+                particle.2 = true;
+            }
+        }
+        let fields: Vec<ForceField> = self.fields.clone();
+        self.solve_chunks(0, |region, x, y| {
+            region.solve_particle(x, y, &fields, frame_time)
+        });
+        self.solve_chunks(1, |region, x, y| {
+            region.solve_heat(x, y, frame_time);
+            region.solve_interactions(x, y, frame_time);
+        });
+        self.solve_edges();
+        // In the rigid body mode the connected solids fall as one object, otherwise every solid stays in place
+        if rigid_bodies {
+            self.solve_rigid_bodies(frame_time);
+        } else {
+            self.bodies.clear();
+        }
+        for awake in self.awake.iter_mut() {
+            *awake = awake.saturating_sub(1);
+        }
+        self.tick += 1;
+    }
+    // Indices of the cells of the awake chunks, one range for every row of every awake chunk
+    pub(crate) fn awake_rows(&self) -> Vec<Range<usize>> {
+        let (width, height) = (self.get_width() as usize, self.get_height() as usize);
+        let chunk_cols: usize = self.chunk_cols();
+        let mut rows: Vec<Range<usize>> = vec![];
+        for chunk in 0..self.awake.len() {
+            if self.awake[chunk] == 0 {
+                continue;
+            }
+            let (chunk_row, chunk_col) = (chunk / chunk_cols, chunk % chunk_cols);
+            let cols = chunk_col * CHUNK_SIZE..((chunk_col + 1) * CHUNK_SIZE).min(width);
+            for i in chunk_row * CHUNK_SIZE..((chunk_row + 1) * CHUNK_SIZE).min(height) {
+                rows.push(i * width + cols.start..i * width + cols.end);
+            }
+        }
+        rows
+    }
+    fn chunk_cols(&self) -> usize {
+        (self.get_width() as usize).div_ceil(CHUNK_SIZE)
+    }
+    // Every chunk starts awake, e.g. after the game_board was created or resized
+    pub(crate) fn wake_all(&mut self) {
+        let chunk_count: usize =
+            (self.get_height() as usize).div_ceil(CHUNK_SIZE) * self.chunk_cols();
+        self.awake = vec![SLEEP_DELAY; chunk_count];
+    }
+    // Wakes the chunk of the cell and its neighbouring chunks, it's called for every cell changed outside of the
+    // simulation (e.g. by the user or a rigid body)
+    pub(crate) fn wake(&mut self, index: usize) {
+        let width: usize = self.get_width() as usize;
+        self.wake_chunk(index / width / CHUNK_SIZE, index % width / CHUNK_SIZE);
+    }
+    // Wakes the chunk and the chunks around it, including the ones behind the wrapping edges
+    fn wake_chunk(&mut self, chunk_row: usize, chunk_col: usize) {
+        let chunk_cols: usize = self.chunk_cols();
+        let chunk_rows: usize = self.awake.len() / chunk_cols;
+        let boundaries: Boundaries = self.boundaries;
+        let cols: Vec<usize> =
+            neighbour_chunks(chunk_col, chunk_cols, boundaries.left, boundaries.right);
+        for row in neighbour_chunks(chunk_row, chunk_rows, boundaries.top, boundaries.bottom) {
+            for col in &cols {
+                self.awake[row * chunk_cols + col] = SLEEP_DELAY;
+            }
+        }
+    }
+    // Calls the solver for every cell (column, row) of the awake chunks, the game_board is split into chunks, which are
+    // solved in 4 rounds in a checkerboard pattern, so the chunks solved at the same time are never neighbours and a
+    // particle can't reach the cells of another chunk solved at the same time. The chunk rows of a round are solved in
+    // parallel, the results are the same with any number of threads (it can be set with the RAYON_NUM_THREADS
+    // variable). The rows of the chunk rows with the same parity don't overlap, so one task solves both column rounds
+    // of its chunk row, the threads only wait for each other twice per pass and the rows stay in the cache of the same
+    // thread. Through a wrapping top or bottom edge the first and the last chunk row reach each other, so then a single
+    // task holds all rows and solves the chunk rows one after another. The chunks, where something changed, wake their
+    // neighbours, the other ones slowly fall asleep
+    fn solve_chunks(&mut self, pass: u64, solve: impl Fn(&mut Region, i32, i32) + Sync) {
+        let width: usize = self.get_width() as usize;
+        let height: usize = self.get_height() as usize;
+        let chunk_row_count: usize = height.div_ceil(CHUNK_SIZE);
+        let chunk_col_count: usize = self.chunk_cols();
+        let (seed, tick) = (self.seed, self.tick);
+        let boundaries: Boundaries = self.boundaries;
+        let wraps_vertically: bool =
+            boundaries.top == EdgeMode::Wrap || boundaries.bottom == EdgeMode::Wrap;
+        // The columns are scanned in alternating directions, so the particles don't lean towards one side
+        let reverse: bool = tick % 2 == 1;
+        for row_parity in [0, 1] {
+            let chunk_rows = (row_parity..chunk_row_count).step_by(2);
+            // Every chunk row gets its rows and the rows within the reach of its particles
+            let mut windows: Vec<(Vec<usize>, Cells)> = vec![];
+            if wraps_vertically {
+                windows.push((chunk_rows.collect(), Cells::new(0, &mut self.contents)));
+            } else {
+                let mut rest: &mut [Particle] = &mut self.contents;
+                let mut rest_row: usize = 0;
+                for chunk_row in chunk_rows {
+                    let first_row: usize =
+                        (chunk_row * CHUNK_SIZE).saturating_sub(MAX_REACH as usize);
+                    let end_row: usize =
+                        ((chunk_row + 1) * CHUNK_SIZE + MAX_REACH as usize).min(height);
+                    let (_, tail) =
+                        std::mem::take(&mut rest).split_at_mut((first_row - rest_row) * width);
+                    let (cells, tail) = tail.split_at_mut((end_row - first_row) * width);
+                    rest = tail;
+                    rest_row = end_row;
+                    windows.push((vec![chunk_row], Cells::new(first_row * width, cells)));
+                }
+            }
+            let awake: &[u8] = &self.awake;
+            let results: Vec<_> = windows
+                .into_par_iter()
+                .map(|(chunk_rows, contents)| {
+                    let mut region = Region {
+                        width: width as u16,
+                        height: height as u16,
+                        contents,
+                        reactions: vec![],
+                        rng: StdRng::seed_from_u64(0),
+                        boundaries,
+                    };
+                    let mut changed_chunks: Vec<(usize, usize)> = vec![];
+                    for chunk_row in chunk_rows {
+                        // The chunks woken in the first column round are solved in the second one
+                        let mut awake_cols: Vec<bool> = (0..chunk_col_count)
+                            .map(|chunk_col| awake[chunk_row * chunk_col_count + chunk_col] != 0)
+                            .collect();
+                        for col_parity in [0, 1] {
+                            let mut chunk_cols: Vec<usize> =
+                                (col_parity..chunk_col_count).step_by(2).collect();
+                            if reverse {
+                                chunk_cols.reverse();
+                            }
+                            for chunk_col in chunk_cols {
+                                if !awake_cols[chunk_col] {
+                                    continue;
+                                }
+                                let reaction_count: usize = region.reactions.len();
+                                region.contents.changed = false;
+                                region.rng = chunk_rng(seed, tick, pass, chunk_row, chunk_col);
+                                let rows = chunk_row * CHUNK_SIZE
+                                    ..((chunk_row + 1) * CHUNK_SIZE).min(height);
+                                let cols = chunk_col * CHUNK_SIZE
+                                    ..((chunk_col + 1) * CHUNK_SIZE).min(width);
+                                for i in rows {
+                                    for k in 0..cols.len() {
+                                        let j: usize = if reverse {
+                                            cols.end - 1 - k
+                                        } else {
+                                            cols.start + k
+                                        };
+                                        solve(&mut region, j as i32, i as i32);
+                                    }
+                                }
+                                if region.contents.changed
+                                    || region.reactions.len() != reaction_count
+                                {
+                                    changed_chunks.push((chunk_row, chunk_col));
+                                    for col in neighbour_chunks(
+                                        chunk_col,
+                                        chunk_col_count,
+                                        boundaries.left,
+                                        boundaries.right,
+                                    ) {
+                                        awake_cols[col] = true;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    (region.reactions, region.contents.moves, changed_chunks)
+                })
+                .collect();
+            for (reactions, moves, changed_chunks) in results {
+                self.reactions.extend(reactions);
+                self.moves += moves;
+                for (chunk_row, chunk_col) in changed_chunks {
+                    self.wake_chunk(chunk_row, chunk_col);
+                }
+            }
+        }
+    }
+}
+
+// The chunk column (or row) and its neighbours among the count of them, the particles can move through the wrapping
+// edges before the first and after the last one, so the chunks on the opposite side are included too
+fn neighbour_chunks(chunk: usize, count: usize, first: EdgeMode, last: EdgeMode) -> Vec<usize> {
+    let mut chunks: Vec<usize> = (chunk.saturating_sub(1)..(chunk + 2).min(count)).collect();
+    if chunk == 0 && first == EdgeMode::Wrap && !chunks.contains(&(count - 1)) {
+        chunks.push(count - 1);
+    }
+    if chunk == count - 1 && last == EdgeMode::Wrap && !chunks.contains(&0) {
+        chunks.push(0);
+    }
+    chunks
+}
+
+// Every chunk gets its own random numbers in every frame, so they don't depend on the order of the chunks. The seed
+// of the generator mixes the numbers one by one with SplitMix64, so it's the same on every platform and every version
+// of the standard library (unlike its hashers)
+pub(crate) fn chunk_rng(
+    seed: u64,
+    tick: u64,
+    pass: u64,
+    chunk_row: usize,
+    chunk_col: usize,
+) -> StdRng {
+    let state: u64 = [tick, pass, chunk_row as u64, chunk_col as u64]
+        .into_iter()
+        .fold(splitmix64(seed), |state, value| splitmix64(state ^ value));
+    StdRng::seed_from_u64(state)
+}
+
+// One step of the SplitMix64 generator, it spreads every bit of the input over the whole output
+pub(crate) fn splitmix64(state: u64) -> u64 {
+    let mut z: u64 = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use ::rand::Rng;
+
+    use super::*;
+    use crate::{materials, scene};
+
+    const WIDTH: u16 = 100; // 4 chunk columns and rows, so every round solves several windows and chunks at once
+    const HEIGHT: u16 = 100;
+    const TICKS: u32 = 100;
+    const FILL_CHANCE: f64 = 0.3;
+
+    // Board filled from the seed, with the top and bottom edges wrapping or not
+    fn random_board(seed: u64, wrap: bool) -> Board {
+        let all_materials: Vec<crate::Material> = materials::CATEGORIES
+            .iter()
+            .flat_map(|(_, materials)| materials.iter().map(|material| **material))
+            .collect();
+        let mut rng: StdRng = StdRng::seed_from_u64(seed);
+        let mut game_board: Board = Board::new(WIDTH, HEIGHT);
+        game_board.seed = seed;
+        if wrap {
+            (game_board.boundaries.top, game_board.boundaries.bottom) =
+                (EdgeMode::Wrap, EdgeMode::Wrap);
+        }
+        for count in 0..game_board.contents.len() {
+            game_board.contents[count].3 = rng.gen_range(0.0..1.0);
+            if rng.gen_bool(FILL_CHANCE) {
+                let material = all_materials[rng.gen_range(0..all_materials.len())];
+                let (x, y) = (
+                    (count % WIDTH as usize) as i32,
+                    (count / WIDTH as usize) as i32,
+                );
+                game_board.paint_cell(x, y, material);
+            }
+        }
+        game_board
+    }
+
+    // Steps the board in a pool of the threads, returns the encoded scene
+    fn solve_in_pool(mut game_board: Board, threads: usize) -> Vec<u8> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            for _ in 0..TICKS {
+                game_board.step(1.0 / 60.0, false);
+            }
+        });
+        scene::encode(&game_board)
+    }
+
+    #[test]
+    fn the_number_of_threads_doesnt_change_the_result() {
+        for wrap in [false, true] {
+            let single: Vec<u8> = solve_in_pool(random_board(7, wrap), 1);
+            let multiple: Vec<u8> = solve_in_pool(random_board(7, wrap), 4);
+            assert!(single == multiple, "the boards differ with wrap {wrap}");
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+
+use crate::boundaries::Boundaries;
+use crate::settings::Args;
+use crate::{materials, Board};
+
+const MAX_REPORTS: usize = 5; // Number of the last violations shown on the screen
+const DEFAULT_BOARDS: u32 = 50; // Number of random boards run by the harness, when it isn't given
+const DEFAULT_TICKS: u32 = 300;
+const FILL_CHANCE: f32 = 0.4; // Chance, that a cell of a random board gets a particle
+
+// Number of the particles of every material on the game_board, the void isn't counted
+pub(crate) fn count_materials(game_board: &Board) -> BTreeMap<&'static str, i64> {
+    let mut counts: BTreeMap<&'static str, i64> = BTreeMap::new();
+    for particle in &game_board.contents {
+        if particle.0.name != materials::solid::VOID.name {
+            *counts.entry(particle.0.name).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// Simulates one frame and checks, that no particle was created or destroyed, except by the reactions (e.g. sand
+// soaking up water and turning into mud), the user edits aren't made during the step, so they can't cause an error
+pub(crate) fn checked_step(
+    game_board: &mut Board,
+    frame_time: f32,
+    rigid_bodies: bool,
+) -> Result<(), String> {
+    let mut expected: BTreeMap<&'static str, i64> = count_materials(game_board);
+    game_board.step(frame_time, rigid_bodies);
+    for (before, after) in &game_board.reactions {
+        *expected.entry(before).or_insert(0) -= 1;
+        *expected.entry(after).or_insert(0) += 1;
+    }
+    expected.remove(materials::solid::VOID.name);
+    let counts: BTreeMap<&'static str, i64> = count_materials(game_board);
+    let differences: Vec<String> = expected
+        .keys()
+        .chain(counts.keys())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|name| {
+            let (expected, count) = (
+                expected.get(name).copied().unwrap_or(0),
+                counts.get(name).copied().unwrap_or(0),
+            );
+            (expected != count).then(|| format!("{name} {:+}", count - expected))
+        })
+        .collect();
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "tick {}: {}",
+            game_board.tick - 1,
+            differences.join(", ")
+        ))
+    }
+}
+
+// Debug mode toggled with the F5 key, it checks the conservation of the particles in every frame and shows the
+// violations
+#[derive(Default)]
+pub(crate) struct ConservationCheck {
+    pub(crate) enabled: bool,
+    violations: u64, // Number of the frames, in which particles disappeared or were duplicated
+    reports: Vec<String>, // Descriptions of the last violations
+}
+
+impl ConservationCheck {
+    pub(crate) fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::F5) {
+            self.enabled = !self.enabled;
+            self.violations = 0;
+            self.reports.clear();
+        }
+    }
+    // Simulates one frame, with the checking only when it's enabled, because counting the particles is slow
+    pub(crate) fn step(&mut self, game_board: &mut Board, frame_time: f32, rigid_bodies: bool) {
+        if !self.enabled {
+            game_board.step(frame_time, rigid_bodies);
+            return;
+        }
+        if let Err(report) = checked_step(game_board, frame_time, rigid_bodies) {
+            eprintln!("Particle conservation violated at {report}");
+            self.violations += 1;
+            self.reports.push(report);
+            if self.reports.len() > MAX_REPORTS {
+                self.reports.remove(0);
+            }
+        }
+    }
+    pub(crate) fn draw(&self, x: f32, y: f32) {
+        if !self.enabled {
+            return;
+        }
+        let color = if self.violations == 0 { GREEN } else { ORANGE };
+        draw_text(
+            &format!("Conservation violations: {}", self.violations),
+            x,
+            y,
+            20.0,
+            color,
+        );
+        for (row, report) in self.reports.iter().enumerate() {
+            draw_text(report, x, y + 18.0 * (row + 1) as f32, 18.0, color);
+        }
+    }
+}
+
+// The "check-conservation" command, it runs random boards through many ticks without a window and exits with an
+// error, when a particle was created or destroyed
+pub(crate) fn run_harness(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let (mut boards, mut ticks, mut seed): (u32, u32, u64) = (DEFAULT_BOARDS, DEFAULT_TICKS, 0);
+    let mut args = Args::new(args);
+    while let Some(option) = args.next()? {
+        match option.as_str() {
+            "--boards" => boards = args.number()?,
+            "--ticks" => ticks = args.number()?,
+            "--seed" => seed = args.number()?,
+            _ => return Err(format!("Unknown option \"{option}\"")),
+        }
+    }
+    check_random_boards(boards, ticks, seed)?;
+    println!("{boards} boards conserved their particles for {ticks} ticks");
+    Ok(())
+}
+
+// Fills random boards (their size, edges, particles and the rigid body mode) and checks the conservation in every
+// tick, the boards are generated from their own random numbers, so a failing board is the same in every run
+pub(crate) fn check_random_boards(boards: u32, ticks: u32, seed: u64) -> Result<(), String> {
+    let all_materials: Vec<crate::Material> = materials::CATEGORIES
+        .iter()
+        .flat_map(|(_, materials)| materials.iter().map(|material| **material))
+        .collect();
+    for board in 0..boards {
+        // Every board has its own seed, so a failing board can be run again alone
+        let board_seed: u64 = seed + board as u64;
+        let mut rng: StdRng = StdRng::seed_from_u64(board_seed);
+        let mut game_board: Board = Board::new(rng.gen_range(10..80), rng.gen_range(10..60));
+        game_board.seed = board_seed;
+        game_board.boundaries = Boundaries::from_bytes([(); 4].map(|_| rng.gen_range(0..3)));
+        for count in 0..game_board.contents.len() {
+            game_board.contents[count].3 = rng.gen_range(0.0..1.0);
+            if rng.gen_range(0.0..1.0) < FILL_CHANCE {
+                let material = all_materials[rng.gen_range(0..all_materials.len())];
+                let (x, y) = (
+                    (count % game_board.width as usize) as i32,
+                    (count / game_board.width as usize) as i32,
+                );
+                game_board.paint_cell(x, y, material);
+            }
+        }
+        game_board.edits.clear();
+        let rigid_bodies: bool = rng.gen_range(0..2) == 1;
+        for _ in 0..ticks {
+            checked_step(&mut game_board, 1.0 / 60.0, rigid_bodies).map_err(|report| {
+                format!(
+                    "Particle conservation violated on board {board} (--seed {board_seed} --boards 1) at {report}"
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_random_boards;
+
+    const TEST_BOARDS: u32 = 40; // Fewer boards and ticks than the command runs, so the tests are quick
+    const TEST_TICKS: u32 = 150;
+
+    #[test]
+    fn random_boards_conserve_particles() {
+        if let Err(report) = check_random_boards(TEST_BOARDS, TEST_TICKS, 0) {
+            panic!("{report}");
+        }
+    }
+}
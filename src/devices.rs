@@ -0,0 +1,135 @@
+use macroquad::prelude::*;
+
+use crate::neighbourhood::Direction;
+use crate::scene::Reader;
+use crate::{draw_button, materials, Material, Phase, BUTTON_HEIGHT, BUTTON_SPACING, BUTTON_WIDTH};
+
+const RATES: [f32; 7] = [1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0]; // Rates of the emitters selectable in the panel (particles per second)
+
+// Order, in which the button of the panel turns the side of an emitter
+const CLOCKWISE: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+// What an emitter spawns, it's a part of its material, so every emitter cell keeps the emission it was painted with
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct Emission {
+    pub(crate) material: &'static str, // Name of the spawned material
+    pub(crate) rate: f32,              // Average number of the particles spawned per second
+    pub(crate) direction: Direction,   // Side of the emitter, where the particles appear
+}
+
+// The materials, which an emitter can spawn: the materials of the palette, which aren't solid
+fn emittable() -> impl Iterator<Item = &'static Material> {
+    materials::CATEGORIES
+        .iter()
+        .flat_map(|(_, materials)| materials.iter().copied())
+        .filter(|material| !matches!(material.phase, Phase::Void | Phase::Solid))
+}
+
+// Draws the spawned material, the side and the rate of the selected emitter material, they're changed in the
+// selected material, so the next painted emitters get them
+pub(crate) fn draw_emitter_panel(selected_material: &mut Material, x: f32, y: f32) {
+    let Some(emission) = selected_material.emits.as_mut() else {
+        return;
+    };
+    let spawned: Vec<&Material> = emittable().collect();
+    let position: usize = spawned
+        .iter()
+        .position(|material| material.name == emission.material)
+        .unwrap_or(0);
+    if draw_button(
+        emission.material,
+        x,
+        y,
+        BUTTON_WIDTH,
+        BUTTON_HEIGHT,
+        DARKGRAY,
+    ) {
+        emission.material = spawned[(position + 1) % spawned.len()].name;
+    }
+    if let Some(material) = spawned
+        .iter()
+        .find(|material| material.name == emission.material)
+    {
+        draw_rectangle(x + BUTTON_WIDTH - 16.0, y + 9.0, 12.0, 12.0, material.color);
+    }
+    let row_y = y + BUTTON_SPACING;
+    if draw_button(
+        &format!("{:?}", emission.direction),
+        x,
+        row_y,
+        BUTTON_WIDTH,
+        BUTTON_HEIGHT,
+        DARKGRAY,
+    ) {
+        let side: usize = CLOCKWISE
+            .iter()
+            .position(|direction| *direction == emission.direction)
+            .unwrap_or(0);
+        emission.direction = CLOCKWISE[(side + 1) % CLOCKWISE.len()];
+    }
+    let row_y = row_y + BUTTON_SPACING;
+    draw_text(
+        &format!("Rate: {}", emission.rate),
+        x,
+        row_y + 20.0,
+        20.0,
+        WHITE,
+    );
+    let rate: usize = RATES
+        .iter()
+        .position(|rate| *rate >= emission.rate)
+        .unwrap_or(RATES.len() - 1);
+    if draw_button("-", x + 65.0, row_y, 20.0, BUTTON_HEIGHT, DARKGRAY) {
+        emission.rate = RATES[rate.saturating_sub(1)];
+    }
+    if draw_button("+", x + 88.0, row_y, 20.0, BUTTON_HEIGHT, DARKGRAY) {
+        emission.rate = RATES[(rate + 1).min(RATES.len() - 1)];
+    }
+}
+
+// The emission is saved after the name of every material in the scene and replay files: 0 for the materials without
+// one, or 1, the length and the name of the spawned material, the rate and the side (its index in Direction::SIDES)
+pub(crate) fn encode_emission(bytes: &mut Vec<u8>, emits: Option<Emission>) {
+    let Some(emission) = emits else {
+        bytes.push(0);
+        return;
+    };
+    bytes.push(1);
+    bytes.push(emission.material.len() as u8);
+    bytes.extend_from_slice(emission.material.as_bytes());
+    bytes.extend_from_slice(&emission.rate.to_le_bytes());
+    let side = Direction::SIDES
+        .iter()
+        .position(|direction| *direction == emission.direction);
+    bytes.push(side.unwrap_or(0) as u8);
+}
+
+pub(crate) fn decode_emission(reader: &mut Reader) -> Result<Option<Emission>, String> {
+    let read_error = || "the file ends too early".to_string();
+    let [flag] = reader.take().ok_or_else(read_error)?;
+    if flag == 0 {
+        return Ok(None);
+    }
+    let [length] = reader.take().ok_or_else(read_error)?;
+    let name = reader.slice(length as usize).ok_or_else(read_error)?;
+    let name = String::from_utf8_lossy(name);
+    let material: &'static str = emittable()
+        .find(|material| material.name == name)
+        .ok_or_else(|| format!("unknown emitted material \"{name}\""))?
+        .name;
+    let rate = f32::from_le_bytes(reader.take().ok_or_else(read_error)?);
+    let [side] = reader.take().ok_or_else(read_error)?;
+    let direction: Direction = *Direction::SIDES
+        .get(side as usize)
+        .ok_or_else(|| format!("unknown side of an emitter {side}"))?;
+    Ok(Some(Emission {
+        material,
+        rate,
+        direction,
+    }))
+}
@@ -0,0 +1,247 @@
+use macroquad::prelude::*;
+
+use crate::camera::BoardCamera;
+use crate::chunks::{Region, CHUNK_SIZE, MAX_REACH};
+use crate::neighbourhood::{Direction, Neighbourhood};
+use crate::replay::Action;
+use crate::{draw_button, Board, Particle, Phase, BUTTON_HEIGHT, BUTTON_SPACING, BUTTON_WIDTH};
+
+const STRENGTHS: [f32; 6] = [5.0, 10.0, 20.0, 40.0, 80.0, 160.0]; // Strengths selectable in the panel
+pub(crate) const FIELD_BYTES: usize = 17; // Size of a saved force field (the kind, 3 i32 and an f32 number)
+
+// How a force field pushes the particles
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum FieldKind {
+    Wind(Direction), // Pushes in one direction
+    Vortex,          // Pushes clockwise around the centre
+    Attractor,       // Pulls towards the centre
+    Repeller,        // Pushes away from the centre
+}
+
+impl FieldKind {
+    const ALL: [FieldKind; 7] = [
+        FieldKind::Wind(Direction::Up),
+        FieldKind::Wind(Direction::Right),
+        FieldKind::Wind(Direction::Down),
+        FieldKind::Wind(Direction::Left),
+        FieldKind::Vortex,
+        FieldKind::Attractor,
+        FieldKind::Repeller,
+    ];
+    pub(crate) fn name(self) -> String {
+        match self {
+            FieldKind::Wind(direction) => format!("Wind {direction:?}"),
+            kind => format!("{kind:?}"),
+        }
+    }
+    // Reads the kind by its name (e.g. "vortex" or "wind left")
+    pub(crate) fn parse(name: &str) -> Result<FieldKind, String> {
+        FieldKind::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or(format!(
+                "Unknown force field \"{name}\", it can be wind up, wind right, wind down, wind left, vortex, attractor or repeller"
+            ))
+    }
+    fn color(self) -> Color {
+        match self {
+            FieldKind::Wind(_) => WHITE,
+            FieldKind::Vortex => MAGENTA,
+            FieldKind::Attractor => LIME,
+            FieldKind::Repeller => PINK,
+        }
+    }
+}
+
+// A circular region placed by the user, which pushes the movable particles inside it
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct ForceField {
+    pub(crate) kind: FieldKind,
+    pub(crate) x: i32, // Column and row of the centre
+    pub(crate) y: i32,
+    pub(crate) radius: i32,   // 0 covers only the centre
+    pub(crate) strength: f32, // Acceleration of a particle with the mass 1 (in cells per frame per second, like the gravity)
+}
+
+impl Default for ForceField {
+    fn default() -> Self {
+        ForceField {
+            kind: FieldKind::Wind(Direction::Right),
+            x: 0,
+            y: 0,
+            radius: 0,
+            strength: 20.0,
+        }
+    }
+}
+
+impl ForceField {
+    // Whether the cell is inside the field, the same circle as a stamp of the brush
+    fn contains(&self, x: i32, y: i32) -> bool {
+        let (dx, dy) = (x - self.x, y - self.y);
+        dx * dx + dy * dy <= self.radius * self.radius + self.radius
+    }
+    // The force in the cell, zero outside of the field and in the centre of the round fields
+    fn force(&self, x: i32, y: i32) -> Vec2 {
+        if !self.contains(x, y) {
+            return Vec2::ZERO;
+        }
+        let outwards: Vec2 = vec2((x - self.x) as f32, (y - self.y) as f32).normalize_or_zero();
+        let direction: Vec2 = match self.kind {
+            FieldKind::Wind(direction) => {
+                let (dx, dy) = direction.offset();
+                vec2(dx as f32, dy as f32)
+            }
+            // The rows grow downwards, so this turns clockwise on the screen
+            FieldKind::Vortex => vec2(-outwards.y, outwards.x),
+            FieldKind::Attractor => -outwards,
+            FieldKind::Repeller => outwards,
+        };
+        direction * self.strength
+    }
+    // The kind (its index in FieldKind::ALL), the centre, the radius and the strength, all numbers are little endian
+    pub(crate) fn to_bytes(self) -> [u8; FIELD_BYTES] {
+        let mut bytes: [u8; FIELD_BYTES] = [0; FIELD_BYTES];
+        bytes[0] = FieldKind::ALL
+            .iter()
+            .position(|kind| *kind == self.kind)
+            .unwrap_or(0) as u8;
+        for (number, value) in [self.x, self.y, self.radius].into_iter().enumerate() {
+            bytes[1 + number * 4..5 + number * 4].copy_from_slice(&value.to_le_bytes());
+        }
+        bytes[13..].copy_from_slice(&self.strength.to_le_bytes());
+        bytes
+    }
+    pub(crate) fn from_bytes(bytes: [u8; FIELD_BYTES]) -> Result<ForceField, String> {
+        let number = |start: usize| {
+            i32::from_le_bytes([
+                bytes[start],
+                bytes[start + 1],
+                bytes[start + 2],
+                bytes[start + 3],
+            ])
+        };
+        Ok(ForceField {
+            kind: *FieldKind::ALL
+                .get(bytes[0] as usize)
+                .ok_or_else(|| format!("unknown force field {}", bytes[0]))?,
+            x: number(1),
+            y: number(5),
+            radius: number(9),
+            strength: f32::from_le_bytes([bytes[13], bytes[14], bytes[15], bytes[16]]),
+        })
+    }
+}
+
+impl Board {
+    // The fields are recorded as actions, so they're placed again, when a replay is played back
+    pub(crate) fn add_field(&mut self, field: ForceField) {
+        self.actions.push(Action::AddField(field));
+        self.fields.push(field);
+    }
+    // Removes every field, which covers the cell
+    pub(crate) fn remove_fields(&mut self, x: i32, y: i32) {
+        self.actions.push(Action::RemoveFields { x, y });
+        self.fields.retain(|field| !field.contains(x, y));
+    }
+    // The fields keep their chunks awake, so they push the settled particles too
+    pub(crate) fn wake_fields(&mut self) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        for field in self.fields.clone() {
+            let rows = (field.y - field.radius).max(0)..=(field.y + field.radius).min(height - 1);
+            let cols = (field.x - field.radius).max(0)..=(field.x + field.radius).min(width - 1);
+            for row in rows.step_by(CHUNK_SIZE) {
+                for col in cols.clone().step_by(CHUNK_SIZE) {
+                    self.wake((row * width + col) as usize);
+                }
+            }
+        }
+    }
+}
+
+impl Region<'_> {
+    // Adds the force of the fields to the velocity of the particle, the lighter particles are accelerated more than the
+    // heavy ones. The phase behaviours move the particle along its velocity, like they do with the gravity
+    pub(crate) fn apply_fields(&mut self, fields: &[ForceField], x: i32, y: i32, frame_time: f32) {
+        let Some(cell) = self.index(x, y) else {
+            return;
+        };
+        let particle: &Particle = &self.contents[cell];
+        if matches!(particle.0.phase, Phase::Void | Phase::Solid) || !particle.2 {
+            return;
+        }
+        let force: Vec2 = fields.iter().map(|field| field.force(x, y)).sum();
+        if force == Vec2::ZERO {
+            return;
+        }
+        let acceleration: Vec2 = force / particle.0.mass;
+        let reach: f32 = MAX_REACH as f32;
+        self.contents[cell].1 = (self.contents[cell].1 + acceleration * frame_time)
+            .clamp(Vec2::splat(-reach), Vec2::splat(reach));
+    }
+}
+
+// Outlines the fields on the game_board, the winds get an arrow in their direction
+pub(crate) fn draw_fields(game_board: &Board, camera: &BoardCamera) {
+    for field in &game_board.fields {
+        let center: Vec2 = camera.board_to_screen(vec2(field.x as f32 + 0.5, field.y as f32 + 0.5));
+        let radius: f32 = (field.radius as f32 + 0.5) * camera.zoom;
+        let color: Color = field.kind.color();
+        draw_circle_lines(center.x, center.y, radius, 1.0, color);
+        if let FieldKind::Wind(direction) = field.kind {
+            let (dx, dy) = direction.offset();
+            let tip: Vec2 = center + vec2(dx as f32, dy as f32) * radius;
+            draw_line(center.x, center.y, tip.x, tip.y, 1.0, color);
+        }
+    }
+}
+
+// Draws the kind and the strength of the fields placed with the field tool
+pub(crate) fn draw_field_panel(field: &mut ForceField, x: f32, y: f32) {
+    if draw_button(
+        &field.kind.name(),
+        x,
+        y,
+        BUTTON_WIDTH,
+        BUTTON_HEIGHT,
+        DARKGRAY,
+    ) {
+        let kind: usize = FieldKind::ALL
+            .iter()
+            .position(|kind| *kind == field.kind)
+            .unwrap_or(0);
+        field.kind = FieldKind::ALL[(kind + 1) % FieldKind::ALL.len()];
+    }
+    let row_y = y + BUTTON_SPACING;
+    draw_text(
+        &format!("Force: {}", field.strength),
+        x,
+        row_y + 20.0,
+        20.0,
+        WHITE,
+    );
+    let strength: usize = STRENGTHS
+        .iter()
+        .position(|strength| *strength >= field.strength)
+        .unwrap_or(STRENGTHS.len() - 1);
+    if draw_button(
+        "-",
+        x + BUTTON_WIDTH + 5.0,
+        row_y,
+        20.0,
+        BUTTON_HEIGHT,
+        DARKGRAY,
+    ) {
+        field.strength = STRENGTHS[strength.saturating_sub(1)];
+    }
+    if draw_button(
+        "+",
+        x + BUTTON_WIDTH + 28.0,
+        row_y,
+        20.0,
+        BUTTON_HEIGHT,
+        DARKGRAY,
+    ) {
+        field.strength = STRENGTHS[(strength + 1).min(STRENGTHS.len() - 1)];
+    }
+}
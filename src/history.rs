@@ -0,0 +1,187 @@
+use std::collections::{HashMap, VecDeque};
+
+use macroquad::prelude::*;
+
+use crate::devices::Emission;
+use crate::replay::Action;
+use crate::{materials, Board, Material, Particle};
+
+const MAX_EDITS: usize = 100; // Number of edits, which can be undone
+const SNAPSHOT_INTERVAL: f32 = 0.5; // Time between two snapshots of the simulation (in seconds)
+const MAX_SNAPSHOTS: usize = 10; // Number of snapshots kept, so the last 5 seconds can be rewound
+
+// Cells changed by one user edit (e.g. a brush stroke or clearing the game_board)
+struct Edit {
+    changes: Vec<(usize, Cell, Cell)>, // Index of the cell, the particle before and after the edit
+}
+
+// Particle kept by the history, only the name of its material is stored, so an edit of a large area stays small, the
+// material is looked up again, when the particle is restored
+#[derive(Copy, Clone)]
+struct Cell {
+    material: &'static str,
+    emits: Option<Emission>,
+    velocity: Vec2,
+    random: f32,
+    moisture: f32,
+    temperature: f32,
+}
+
+impl Cell {
+    fn new(particle: &Particle) -> Cell {
+        Cell {
+            material: particle.0.name,
+            emits: particle.0.emits,
+            velocity: particle.1,
+            random: particle.3,
+            moisture: particle.4,
+            temperature: particle.5,
+        }
+    }
+    fn particle(&self) -> Particle {
+        let mut material: Material =
+            materials::find(self.material).unwrap_or(materials::solid::VOID);
+        material.emits = self.emits;
+        Particle(
+            material,
+            self.velocity,
+            true,
+            self.random,
+            self.moisture,
+            self.temperature,
+        )
+    }
+    // Whether the cell holds the particle's material with the same emission, the other values of a particle change
+    // all the time
+    fn same_material(&self, particle: &Particle) -> bool {
+        self.material == particle.0.name && self.emits == particle.0.emits
+    }
+}
+
+// Undo/redo stack of the user edits and the optional snapshots of the simulation for rewinding the physics
+#[derive(Default)]
+pub(crate) struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    pub(crate) snapshots_enabled: bool,
+    snapshots: VecDeque<Board>,
+    since_snapshot: f32, // Time passed since the last snapshot (in seconds)
+}
+
+impl History {
+    // Moves the changes recorded by the game_board into a new edit, it's called, when the user finished an edit
+    pub(crate) fn commit(&mut self, game_board: &mut Board) {
+        if game_board.edits.is_empty() {
+            return;
+        }
+        // A cell painted several times during a stroke only keeps its first state before and its last state after
+        let mut changes: Vec<(usize, Cell, Cell)> = vec![];
+        let mut positions: HashMap<usize, usize> = HashMap::new();
+        for (index, before, after) in game_board.edits.drain(..) {
+            match positions.get(&index) {
+                Some(position) => changes[*position].2 = Cell::new(&after),
+                None => {
+                    positions.insert(index, changes.len());
+                    changes.push((index, Cell::new(&before), Cell::new(&after)));
+                }
+            }
+        }
+        changes.retain(|(_, before, after)| {
+            before.material != after.material || before.emits != after.emits
+        });
+        if changes.is_empty() {
+            return;
+        }
+        self.undo.push(Edit { changes });
+        if self.undo.len() > MAX_EDITS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+    // The physics can move the particles after an edit, so only the cells, which still hold the particles left by the
+    // edit, are restored, the particles moved into the other cells aren't overwritten
+    pub(crate) fn undo(&mut self, game_board: &mut Board) {
+        self.commit(game_board);
+        if let Some(edit) = self.undo.pop() {
+            let cells = edit
+                .changes
+                .iter()
+                .rev()
+                .filter(|(index, _, after)| after.same_material(&game_board.contents[*index]))
+                .map(|(index, before, _)| (*index, before.particle()));
+            game_board.apply(&Action::Cells(cells.collect()));
+            self.redo.push(edit);
+        }
+    }
+    pub(crate) fn redo(&mut self, game_board: &mut Board) {
+        self.commit(game_board);
+        if let Some(edit) = self.redo.pop() {
+            let cells = edit
+                .changes
+                .iter()
+                .filter(|(index, before, _)| before.same_material(&game_board.contents[*index]))
+                .map(|(index, _, after)| (*index, after.particle()));
+            game_board.apply(&Action::Cells(cells.collect()));
+            self.undo.push(edit);
+        }
+    }
+    // Forgets everything, e.g. after the game_board was resized and the indices of the cells changed, the changes not
+    // committed yet are dropped too, so they don't end up in the next edit
+    pub(crate) fn clear(&mut self, game_board: &mut Board) {
+        game_board.edits.clear();
+        self.undo.clear();
+        self.redo.clear();
+        self.snapshots.clear();
+    }
+    // Saves a copy of the game_board every SNAPSHOT_INTERVAL seconds of simulation, if the snapshots are enabled
+    pub(crate) fn record_snapshot(&mut self, game_board: &Board, frame_time: f32) {
+        if !self.snapshots_enabled {
+            self.snapshots.clear();
+            return;
+        }
+        self.since_snapshot += frame_time;
+        if self.since_snapshot >= SNAPSHOT_INTERVAL || self.snapshots.is_empty() {
+            self.since_snapshot = 0.0;
+            self.snapshots.push_back(game_board.clone());
+            if self.snapshots.len() > MAX_SNAPSHOTS {
+                self.snapshots.pop_front();
+            }
+        }
+    }
+    // Goes back to the last snapshot of the simulation
+    pub(crate) fn rewind(&mut self, game_board: &mut Board) {
+        if let Some(snapshot) = self.snapshots.pop_back() {
+            *game_board = snapshot;
+            // Every chunk is woken, so the restored cells are simulated and drawn again
+            game_board.wake_all();
+            self.since_snapshot = 0.0;
+        }
+    }
+    // Seconds of simulation, which can be rewound
+    pub(crate) fn rewind_time(&self) -> f32 {
+        self.snapshots.len() as f32 * SNAPSHOT_INTERVAL
+    }
+}
+
+impl Board {
+    // Erases every particle, it's recorded as an edit, so it can be undone
+    pub(crate) fn clear(&mut self) {
+        self.actions.push(Action::Clear);
+        for index in 0..self.contents.len() {
+            if self.contents[index].0.name != materials::solid::VOID.name {
+                let before = self.contents[index];
+                self.contents[index] = Particle(
+                    materials::solid::VOID,
+                    vec2(0.0, 0.0),
+                    false,
+                    before.3,
+                    0.0,
+                    crate::AMBIENT_TEMPERATURE,
+                );
+                self.edits.push((index, before, self.contents[index]));
+            }
+        }
+        self.bodies.clear();
+        self.wake_all();
+    }
+}
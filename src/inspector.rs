@@ -0,0 +1,179 @@
+use macroquad::prelude::*;
+
+use crate::camera::BoardCamera;
+use crate::chunks::CHUNK_SIZE;
+use crate::{draw_board_texture, Board, Phase};
+
+const ARROW_SPACING: f32 = 8.0; // Minimal distance between two velocity arrows on the screen (in pixels)
+const MAX_ARROW_SPEED: f32 = 5.0; // Speed (in cells/frame) shown with the longest arrow
+pub(crate) const PANEL_WIDTH: f32 = 200.0;
+
+// The cell inspector and the debug overlays drawn over the game_board, they can be toggled with the F1-F4 and F6 keys
+#[derive(Default)]
+pub(crate) struct Inspector {
+    pub(crate) show_panel: bool, // Shows the state of the cell under the cursor
+    pub(crate) show_velocity: bool, // Shows the velocity vectors of the particles
+    pub(crate) show_updated: bool, // Highlights the particles, which moved in the last frame
+    pub(crate) show_phases: bool, // Colours the particles according to their phase
+    pub(crate) show_chunks: bool, // Outlines the awake chunks, which are solved in every frame
+}
+
+impl Inspector {
+    pub(crate) fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::F1) {
+            self.show_panel = !self.show_panel;
+        }
+        if is_key_pressed(KeyCode::F2) {
+            self.show_velocity = !self.show_velocity;
+        }
+        if is_key_pressed(KeyCode::F3) {
+            self.show_updated = !self.show_updated;
+        }
+        if is_key_pressed(KeyCode::F4) {
+            self.show_phases = !self.show_phases;
+        }
+        if is_key_pressed(KeyCode::F6) {
+            self.show_chunks = !self.show_chunks;
+        }
+    }
+    pub(crate) fn draw_overlays(&self, game_board: &Board, camera: &BoardCamera) {
+        if self.show_phases {
+            let pixels: Vec<u8> = game_board
+                .contents
+                .iter()
+                .flat_map(|particle| phase_color(particle.0.phase))
+                .collect();
+            draw_board_texture(game_board, camera, &pixels);
+        }
+        if self.show_updated {
+            let pixels: Vec<u8> = game_board
+                .contents
+                .iter()
+                .flat_map(|particle| {
+                    // The flag is set for every particle of an awake chunk before the frame and cleared by a move
+                    if !particle.2 && particle.0.phase != Phase::Void {
+                        [255, 0, 255, 140]
+                    } else {
+                        [0, 0, 0, 0]
+                    }
+                })
+                .collect();
+            draw_board_texture(game_board, camera, &pixels);
+        }
+        if self.show_velocity {
+            self.draw_velocities(game_board, camera);
+        }
+        if self.show_chunks {
+            self.draw_chunks(game_board, camera);
+        }
+    }
+    fn draw_chunks(&self, game_board: &Board, camera: &BoardCamera) {
+        let chunk_cols = (game_board.width as usize).div_ceil(CHUNK_SIZE);
+        for (chunk, awake) in game_board.awake.iter().enumerate() {
+            if *awake == 0 {
+                continue;
+            }
+            let start = vec2(
+                (chunk % chunk_cols * CHUNK_SIZE) as f32,
+                (chunk / chunk_cols * CHUNK_SIZE) as f32,
+            );
+            let end = (start + Vec2::splat(CHUNK_SIZE as f32))
+                .min(vec2(game_board.width as f32, game_board.height as f32));
+            let (top_left, bottom_right) =
+                (camera.board_to_screen(start), camera.board_to_screen(end));
+            draw_rectangle_lines(
+                top_left.x,
+                top_left.y,
+                bottom_right.x - top_left.x,
+                bottom_right.y - top_left.y,
+                1.0,
+                Color::new(0.0, 1.0, 1.0, 0.6),
+            );
+        }
+    }
+    // Draws an arrow for every moving particle, when zoomed out only every n-th cell gets an arrow
+    fn draw_velocities(&self, game_board: &Board, camera: &BoardCamera) {
+        let step = (ARROW_SPACING / camera.zoom).ceil().max(1.0) as usize;
+        let visible = camera.visible_cells(game_board.width, game_board.height);
+        let col_count = game_board.width as usize;
+        for row in (visible.y as usize..(visible.y + visible.h).ceil() as usize).step_by(step) {
+            for col in (visible.x as usize..(visible.x + visible.w).ceil() as usize).step_by(step) {
+                let particle = &game_board.contents[row * col_count + col];
+                let speed = particle.1.length();
+                if particle.0.phase == Phase::Void || speed < 0.01 {
+                    continue;
+                }
+                let start = camera.board_to_screen(vec2(col as f32 + 0.5, row as f32 + 0.5));
+                let length =
+                    speed.min(MAX_ARROW_SPEED) / MAX_ARROW_SPEED * step as f32 * camera.zoom;
+                let end = start + particle.1 / speed * length;
+                let heat = speed.min(MAX_ARROW_SPEED) / MAX_ARROW_SPEED;
+                draw_line(
+                    start.x,
+                    start.y,
+                    end.x,
+                    end.y,
+                    1.0,
+                    Color::new(heat, 1.0 - heat, 0.0, 1.0),
+                );
+                draw_circle(end.x, end.y, 1.5, WHITE);
+            }
+        }
+    }
+    // Shows every field of the particle under the cursor
+    pub(crate) fn draw_panel(&self, game_board: &Board, camera: &BoardCamera, x: f32, y: f32) {
+        if !self.show_panel {
+            return;
+        }
+        let Some(position) = camera.screen_to_board(mouse_position().into()) else {
+            return;
+        };
+        let (col, row) = (position.x.floor(), position.y.floor());
+        if col < 0.0
+            || row < 0.0
+            || col >= game_board.width as f32
+            || row >= game_board.height as f32
+        {
+            return;
+        }
+        let index = row as usize * game_board.width as usize + col as usize;
+        let particle = &game_board.contents[index];
+        let lines: [String; 8] = [
+            format!("Cell: {col}, {row}"),
+            format!("Material: {}", particle.0.name),
+            format!("Phase: {:?}", particle.0.phase),
+            format!("Velocity: {:.2}, {:.2}", particle.1.x, particle.1.y),
+            format!("Moved: {}", !particle.2),
+            format!("Random: {:.3}", particle.3),
+            format!("Moisture: {:.2}", particle.4),
+            format!("Temperature: {:.1} °C", particle.5),
+        ];
+        draw_rectangle(
+            x,
+            y,
+            PANEL_WIDTH,
+            lines.len() as f32 * 18.0 + 8.0,
+            Color::new(0.0, 0.0, 0.0, 0.85),
+        );
+        for (line_index, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                x + 5.0,
+                y + 18.0 * (line_index + 1) as f32,
+                18.0,
+                WHITE,
+            );
+        }
+    }
+}
+
+fn phase_color(phase: Phase) -> [u8; 4] {
+    match phase {
+        Phase::Void => [0, 0, 0, 0],
+        Phase::Solid => [160, 160, 160, 220],
+        Phase::Powder { .. } => [230, 200, 40, 220],
+        Phase::Liquid { .. } => [40, 90, 230, 220],
+        Phase::Gas { .. } => [60, 220, 90, 220],
+        Phase::Plasma { .. } => [220, 60, 220, 220],
+    }
+}
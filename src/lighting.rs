@@ -0,0 +1,191 @@
+use std::f32::consts::TAU;
+
+use macroquad::miniquad::{BlendFactor, BlendState, Equation, PipelineParams};
+use macroquad::prelude::*;
+
+use crate::camera::BoardCamera;
+use crate::renderer::{GLOW_RANGE, GLOW_TEMPERATURE};
+use crate::Board;
+
+const LIGHT_SCALE: usize = 4; // Size of a cell of the light map (in game_board cells)
+const LIGHT_RADIUS: usize = 3; // Distance the light spreads to in one blur pass (in light map cells)
+const BLUR_PASSES: usize = 2; // Repeated box blurs fade the light out smoothly
+const LIGHT_GAIN: f32 = 12.0; // Brightness of the spread light, it makes up for the light spread over many cells
+const GLOW_STRENGTH: f32 = 0.7; // Brightness of the glow added over the lit cells
+const DAY_LENGTH: f32 = 120.0; // Duration of a day and a night (in seconds)
+const NIGHT_AMBIENT: f32 = 0.12; // Ambient light in the middle of the night (0 - dark, 1 - daylight)
+const HEAT_LIGHT: Vec3 = vec3(1.0, 0.45, 0.15); // Colour of the light emitted by the hot particles
+
+const VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    uv = texcoord;
+    color = color0 / 255.0;
+}"#;
+
+const FRAGMENT: &str = r#"#version 100
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = texture2D(Texture, uv) * color;
+}"#;
+
+// The optional lighting mode toggled with the L key, the emissive and hot particles light up their surroundings and
+// glow, the rest of the game_board is lit by the ambient light, which changes between the day and the night
+#[derive(Default)]
+pub(crate) struct Lighting {
+    pub(crate) enabled: bool,
+    time_of_day: f32,  // Time since the last noon (in seconds)
+    light: Vec<Vec3>,  // Light falling onto every cell of the light map
+    spread: Vec<Vec3>, // Light spread in one direction, it's kept, so it isn't allocated in every frame
+    darkness: Vec<u8>, // RGBA pixels of the shadows
+    glow: Vec<u8>,     // RGBA pixels of the glow
+    textures: Option<(Texture2D, Texture2D)>, // Textures of the shadows and the glow
+    size: (u16, u16),  // Size of the light map
+    glow_material: Option<Option<macroquad::material::Material>>, // Adds the glow to the colours below it, it's loaded once
+}
+
+impl Lighting {
+    pub(crate) fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::L) {
+            self.enabled = !self.enabled;
+        }
+    }
+    // Ambient light between NIGHT_AMBIENT at midnight and 1 at noon
+    pub(crate) fn ambient(&self) -> f32 {
+        let daylight = 0.5 + 0.5 * (self.time_of_day / DAY_LENGTH * TAU).cos();
+        NIGHT_AMBIENT + (1.0 - NIGHT_AMBIENT) * daylight
+    }
+    // Draws the shadows and the glow over the game_board, the time of day advances by the frame time
+    pub(crate) fn draw(&mut self, game_board: &Board, camera: &BoardCamera, frame_time: f32) {
+        if !self.enabled {
+            return;
+        }
+        self.time_of_day = (self.time_of_day + frame_time) % DAY_LENGTH;
+        self.collect_light(game_board);
+        self.spread_light();
+        let ambient = self.ambient();
+        self.darkness.clear();
+        self.glow.clear();
+        for light in &self.light {
+            let intensity = light.max_element();
+            let shadow = (1.0 - ambient - intensity).clamp(0.0, 1.0);
+            self.darkness
+                .extend_from_slice(&[0, 0, 0, (shadow * 255.0) as u8]);
+            let glow = (*light * GLOW_STRENGTH).clamp(Vec3::ZERO, Vec3::ONE) * 255.0;
+            self.glow
+                .extend_from_slice(&[glow.x as u8, glow.y as u8, glow.z as u8, 255]);
+        }
+        self.update_textures();
+        let Some((darkness, glow)) = &self.textures else {
+            return;
+        };
+        let cells_per_texel = LIGHT_SCALE as f32;
+        crate::renderer::draw_texture_on_board(darkness, game_board, camera, cells_per_texel);
+        // The glow is only drawn, if the additive material could be loaded
+        if let Some(material) = self.glow_material.get_or_insert_with(load_glow_material) {
+            gl_use_material(material);
+            crate::renderer::draw_texture_on_board(glow, game_board, camera, cells_per_texel);
+            gl_use_default_material();
+        }
+    }
+    // Sums the light emitted by the particles inside every cell of the light map
+    fn collect_light(&mut self, game_board: &Board) {
+        let (width, height) = (game_board.width as usize, game_board.height as usize);
+        let (light_width, light_height) =
+            (width.div_ceil(LIGHT_SCALE), height.div_ceil(LIGHT_SCALE));
+        self.size = (light_width as u16, light_height as u16);
+        self.light.clear();
+        self.light.resize(light_width * light_height, Vec3::ZERO);
+        for (index, particle) in game_board.contents.iter().enumerate() {
+            let heat = ((particle.5 - GLOW_TEMPERATURE.max(particle.0.temperature)) / GLOW_RANGE)
+                .clamp(0.0, 1.0);
+            if particle.0.emission <= 0.0 && heat <= 0.0 {
+                continue;
+            }
+            let color = particle.0.color;
+            let emitted = vec3(color.r, color.g, color.b) * particle.0.emission + HEAT_LIGHT * heat;
+            let (i, j) = (index / width / LIGHT_SCALE, index % width / LIGHT_SCALE);
+            self.light[i * light_width + j] += emitted / (LIGHT_SCALE * LIGHT_SCALE) as f32;
+        }
+    }
+    // Blurs the light map with box blurs in both directions, so the light reaches the cells around the emitters
+    fn spread_light(&mut self) {
+        let (width, height) = (self.size.0 as usize, self.size.1 as usize);
+        let directions = [(1, width), (width, height)];
+        for (step, count) in directions
+            .into_iter()
+            .cycle()
+            .take(directions.len() * BLUR_PASSES)
+        {
+            self.spread.clear();
+            self.spread.resize(self.light.len(), Vec3::ZERO);
+            for index in 0..self.light.len() {
+                let position = index / step % count;
+                let start = position.saturating_sub(LIGHT_RADIUS);
+                let end = (position + LIGHT_RADIUS).min(count - 1);
+                let sum: Vec3 = (start..=end)
+                    .map(|other| self.light[index - position * step + other * step])
+                    .sum();
+                self.spread[index] = sum / (LIGHT_RADIUS * 2 + 1) as f32;
+            }
+            std::mem::swap(&mut self.light, &mut self.spread);
+        }
+        for light in self.light.iter_mut() {
+            *light *= LIGHT_GAIN;
+        }
+    }
+    fn update_textures(&mut self) {
+        let (width, height) = self.size;
+        match &self.textures {
+            Some((darkness, glow))
+                if darkness.width() as u16 == width && darkness.height() as u16 == height =>
+            {
+                darkness.update_from_bytes(width as u32, height as u32, &self.darkness);
+                glow.update_from_bytes(width as u32, height as u32, &self.glow);
+            }
+            _ => {
+                let darkness = Texture2D::from_rgba8(width, height, &self.darkness);
+                let glow = Texture2D::from_rgba8(width, height, &self.glow);
+                darkness.set_filter(FilterMode::Linear);
+                glow.set_filter(FilterMode::Linear);
+                self.textures = Some((darkness, glow));
+            }
+        }
+    }
+}
+
+fn load_glow_material() -> Option<macroquad::material::Material> {
+    let pipeline_params = PipelineParams {
+        color_blend: Some(BlendState::new(
+            Equation::Add,
+            BlendFactor::One,
+            BlendFactor::One,
+        )),
+        ..Default::default()
+    };
+    load_material(
+        ShaderSource::Glsl {
+            vertex: VERTEX,
+            fragment: FRAGMENT,
+        },
+        MaterialParams {
+            pipeline_params,
+            ..Default::default()
+        },
+    )
+    .ok()
+}
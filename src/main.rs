@@ -1,27 +1,125 @@
 use std::ops::Not;
 
+use ::rand::Rng;
 use macroquad::prelude::*;
+mod batch;
+mod behaviors;
+mod boundaries;
+mod brush;
+mod camera;
+mod capture;
+mod chunks;
+mod conservation;
+mod devices;
+mod forces;
+mod history;
+mod inspector;
+mod lighting;
 mod materials;
+mod neighbourhood;
+mod palette;
+mod renderer;
+mod replay;
+mod rigid;
+mod scene;
+mod scripting;
+mod settings;
+mod stats;
 
-const CELLSIZE: u32 = 3;
+use behaviors::MaterialBehavior;
+use brush::Brush;
+use camera::BoardCamera;
+use capture::Capture;
+use conservation::ConservationCheck;
+use history::History;
+use inspector::Inspector;
+use lighting::Lighting;
+use neighbourhood::{Direction, Neighbourhood};
+use palette::Palette;
+use renderer::Renderer;
+use replay::Replay;
+use scripting::Scripting;
+use settings::Settings;
+use stats::Stats;
+
+const BOARD_X: f32 = 5.0; // Position of the top left corner of the game_board on the screen
+const BOARD_Y: f32 = 60.0;
+const PANEL_MARGIN: f32 = 10.0; // Gap between the game_board and the buttons next to it
+const BUTTON_WIDTH: f32 = 100.0;
+const BUTTON_HEIGHT: f32 = 30.0;
+const BUTTON_SPACING: f32 = 40.0; // Vertical distance between the tops of two buttons
 const GRAVITY: f32 = 9.81;
-#[macroquad::main("Particle Simulator")]
+const AMBIENT_TEMPERATURE: f32 = 20.0; // Temperature of the empty board (in °C)
+const BOILING_POINT: f32 = 100.0; // Above this temperature the moisture evaporates from the wet particles (in °C)
+const SATURATION: f32 = 1.0; // Moisture at which an absorbent powder turns into its wet form (e.g. sand into wet sand, wet sand into mud)
+const ABSORPTION_CHANCE: f32 = 0.05; // Chance per frame, that an absorbent powder soaks up a neighbouring liquid particle
+const DRYING_RATE: f32 = 0.5; // Moisture lost per second by a particle above the boiling point
+const WETNESS_COHESION: f32 = 0.8; // How much the moisture reduces the sliding of a powder (0 - no effect, 1 - wet powders don't slide)
+const HEAT_TRANSFER: f32 = 2.0; // Rate of the heat exchange between touching particles (per second)
 
-async fn main() {
-    let mut game_board: Board = Board {
-        width: 1,
-        height: 1,
-        contents: vec![],
+// The commands run without a window are handled before the window is opened
+fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("check-conservation") {
+        args.next();
+        if let Err(error) = conservation::run_harness(args) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.peek().map(String::as_str) == Some("replay") {
+        args.next();
+        if let Err(error) = replay::run_replay(args) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.peek().map(String::as_str) == Some("run") {
+        args.next();
+        if let Err(error) = batch::run(args) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let settings: Settings = match Settings::from_args(args) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!("Usage: Reaction-resonance [--width <columns>] [--height <rows>] [--cell-size <pixels>] [--edges <modes>]");
+            eprintln!("       Reaction-resonance check-conservation [--boards <count>] [--ticks <count>] [--seed <number>]");
+            eprintln!("       Reaction-resonance run <scene file> [--ticks <count>] [--seed <number>] [--rigid-bodies] [--out <scene file>] [--png <file>] [--scale <pixels>] [--gif <file>] [--frames <directory>] [--every <ticks>] [--stats] [--csv <file>] [--csv-interval <ticks>] [--script <file>] [--edges <modes>]");
+            eprintln!("       Reaction-resonance run --script <file> [--width <columns>] [--height <rows>] [options of run]");
+            eprintln!("       Reaction-resonance replay <replay file> [--out <scene file>] [--png <file>] [--scale <pixels>]");
+            std::process::exit(2);
+        }
     };
-    game_board.create_board(300, 200); // Initializes the game_board
-    let mut materials: Vec<Material> = vec![
-        materials::powder::SAND,
-        materials::liquid::WATER,
-        materials::solid::WOOD,
-        materials::gas::METHANE,
-    ];
+    macroquad::Window::new("Particle Simulator", game(settings));
+}
+
+async fn game(mut settings: Settings) {
+    let mut new_settings: Settings = settings; // Settings edited in the settings panel, until they're applied
+    let mut show_settings = false;
+    let mut game_board: Board = Board::new(settings.width, settings.height);
+    game_board.boundaries = settings.boundaries;
+    let mut camera: BoardCamera = BoardCamera::new(&settings);
+    let mut brush: Brush = Brush::default();
+    let mut palette: Palette = Palette::default();
+    let mut history: History = History::default();
+    let mut inspector: Inspector = Inspector::default();
+    let mut conservation: ConservationCheck = ConservationCheck::default();
+    let mut renderer: Renderer = Renderer::default();
+    let mut lighting: Lighting = Lighting::default();
+    let mut capture: Capture = Capture::default();
+    let mut stats: Stats = Stats::default();
+    let mut replay: Replay = Replay::default();
+    let mut scripting: Scripting = Scripting::default();
     let mut is_paused = false;
+    let mut rigid_bodies = false;
     let mut selected_material = materials::gas::METHANE;
+    fit_window(&camera, &palette);
     loop {
         clear_background(RED);
         if is_paused {
@@ -36,120 +134,268 @@ async fn main() {
             draw_text(&format!("FPS: {0}", get_fps()), 40.0, 40.0, 55.0, YELLOW);
             // Displays the FPS of the game
         }
+        if history.snapshots_enabled {
+            draw_text(
+                &format!("Rewind: {:.1} s", history.rewind_time()),
+                240.0,
+                40.0,
+                30.0,
+                WHITE,
+            ); // Displays how many seconds of the simulation can be rewound
+        }
+        conservation.draw(480.0, 20.0);
+        capture.draw(480.0, 40.0); // Displays the last saved screenshot or the progress of the recording
+        replay.draw(720.0, 20.0); // Displays the state of the replay recording or playback
+        scripting.draw(720.0, 40.0); // Displays the running script or its last error
+        if lighting.enabled {
+            draw_text(
+                &format!("Daylight: {:.0} %", lighting.ambient() * 100.0),
+                240.0,
+                20.0,
+                20.0,
+                WHITE,
+            ); // Displays the ambient light of the lighting mode
+        }
 
-        update_board(&mut game_board, &mut selected_material, is_paused);
-        draw_board(&game_board); // This function draws the game_board
-
-        let col_count = game_board.width;
-        draw_clear_button(
-            &mut game_board,
-            (col_count as i32 * CELLSIZE as i32) as f32 + 15.0,
-            60.0,
-        );
-        start_pause_button(
-            &mut is_paused,
-            (col_count as i32 * CELLSIZE as i32) as f32 + 15.0,
-            100.0,
+        camera.handle_input(game_board.width, game_board.height);
+        // The changes made in the last frame are recorded before the next step, so they're played back in order
+        replay.record(&mut game_board, &selected_material, is_paused, rigid_bodies);
+        if replay.is_playing() {
+            replay.play(
+                &mut game_board,
+                &mut selected_material,
+                &mut is_paused,
+                &mut rigid_bodies,
+            );
+        } else {
+            let tick: u64 = game_board.tick;
+            update_board(
+                &mut game_board,
+                &mut selected_material,
+                is_paused,
+                rigid_bodies,
+                &mut brush,
+                &camera,
+                &mut conservation,
+            );
+            // The changes of the script are recorded as actions, so the replays don't run it again
+            if game_board.tick == tick + 1 {
+                scripting.after_step(&mut game_board);
+            }
+        }
+        if !is_paused {
+            history.record_snapshot(&game_board, get_frame_time());
+            capture.record(&game_board);
+        }
+        // The changes are collected, until the mouse buttons are released, so a whole stroke is undone at once
+        if !is_mouse_button_down(MouseButton::Left) && !is_mouse_button_down(MouseButton::Right) {
+            history.commit(&mut game_board);
+        }
+        renderer.draw(&game_board, &camera); // This function draws the game_board
+        boundaries::draw_edges(&game_board, &camera);
+        forces::draw_fields(&game_board, &camera);
+        lighting.draw(
+            &game_board,
+            &camera,
+            if is_paused { 0.0 } else { get_frame_time() },
         );
-        draw_material_buttons(
+        inspector.draw_overlays(&game_board, &camera);
+        stats.update(&game_board, get_frame_time());
+        stats.draw(&camera);
+        brush.draw_preview(&game_board, &camera);
+
+        let panel_x: f32 = panel_x(&camera);
+        draw_clear_button(&mut game_board, panel_x, BOARD_Y);
+        start_pause_button(&mut is_paused, panel_x, BOARD_Y + BUTTON_SPACING);
+        rigid_bodies_button(&mut rigid_bodies, panel_x, BOARD_Y + BUTTON_SPACING * 2.0);
+        if draw_button(
+            "Settings",
+            panel_x,
+            BOARD_Y + BUTTON_SPACING * 3.0,
+            BUTTON_WIDTH,
+            BUTTON_HEIGHT,
+            if show_settings { DARKBLUE } else { DARKGRAY },
+        ) {
+            show_settings = !show_settings;
+            new_settings = settings;
+        }
+        palette.draw(
             &mut selected_material,
-            &mut materials,
-            (col_count as i32 * CELLSIZE as i32) as f32 + 15.0,
-            140.0,
+            panel_x,
+            BOARD_Y + BUTTON_SPACING * 4.0,
+        );
+        brush::draw_brush_panel(&mut brush, panel_x + BUTTON_WIDTH + PANEL_MARGIN, BOARD_Y);
+        // The field tool shows the settings of the fields instead of the emitters
+        if brush.tool == brush::Tool::Field {
+            forces::draw_field_panel(
+                &mut brush.field,
+                panel_x + BUTTON_WIDTH + PANEL_MARGIN,
+                BOARD_Y + BUTTON_SPACING * 7.0,
+            );
+        } else {
+            devices::draw_emitter_panel(
+                &mut selected_material,
+                panel_x + BUTTON_WIDTH + PANEL_MARGIN,
+                BOARD_Y + BUTTON_SPACING * 7.0,
+            );
+        }
+        if show_settings
+            && settings::draw_settings_panel(
+                &mut new_settings,
+                panel_x,
+                BOARD_Y + BUTTON_SPACING * 4.0 + palette.max_height(),
+            )
+        {
+            // The contents of the game_board are kept, it's only cropped or padded to the new size
+            game_board.resize(new_settings.width, new_settings.height);
+            game_board.boundaries = new_settings.boundaries;
+            history.clear(&mut game_board);
+            settings = new_settings;
+            show_settings = false;
+            camera = BoardCamera::new(&settings);
+            fit_window(&camera, &palette);
+        }
+        inspector.draw_panel(
+            &game_board,
+            &camera,
+            panel_x + BUTTON_WIDTH + PANEL_MARGIN,
+            BOARD_Y + BUTTON_SPACING * 10.0,
         );
+        palette.draw_tooltip();
 
-        handle_key_inputs(&mut game_board, &mut is_paused); // This function monitors the pressed keys
+        // The key shortcuts are disabled, while typing into the search box of the palette
+        // While a replay is played back, the user can only stop it
+        if !palette.focused && !replay.is_playing() {
+            handle_key_inputs(
+                &mut game_board,
+                &mut is_paused,
+                &mut rigid_bodies,
+                &mut brush,
+                &mut history,
+            ); // This function monitors the pressed keys
+            inspector.handle_input();
+            conservation.handle_input();
+            lighting.handle_input();
+            stats.handle_input();
+            capture.handle_input(&game_board, settings.cell_size);
+            scripting.handle_input(&mut game_board);
+        }
+        if !palette.focused {
+            // A loaded game_board or replay can have another size, the window is fitted to it like after applying the
+            // settings
+            let replaced: bool =
+                replay.handle_input(&mut game_board, &selected_material, is_paused)
+                    || (!replay.is_playing() && scene::handle_input(&mut game_board));
+            if replaced {
+                history.clear(&mut game_board);
+                settings.width = game_board.width;
+                settings.boundaries = game_board.boundaries;
+                settings.height = game_board.height;
+                new_settings = settings;
+                camera = BoardCamera::new(&settings);
+                fit_window(&camera, &palette);
+            }
+        }
         next_frame().await;
     }
 }
 
-fn draw_board(game_board: &Board) {
-    let f: Vec<_> = game_board
-        .contents
-        .iter()
-        .flat_map(|particle| {
-            let color = vec![
-                (particle.0.color.r * 255.0) as u8,
-                (particle.0.color.g * 255.0) as u8,
-                (particle.0.color.b * 255.0) as u8,
-                (particle.0.color.a * 255.0) as u8,
-            ];
-            color
-        })
-        .collect();
-    let board_cells: Texture2D = Texture2D::from_rgba8(game_board.width, game_board.height, &f);
+// The buttons are drawn in a column next to the right side of the game_board
+fn panel_x(camera: &BoardCamera) -> f32 {
+    camera.viewport.right() + PANEL_MARGIN
+}
+
+// Resizes the window, so the game_board, the buttons, the palette and the settings panel fit into it
+fn fit_window(camera: &BoardCamera, palette: &Palette) {
+    // The second column holds the brush panel and the cell inspector
+    let width: f32 = panel_x(camera) + BUTTON_WIDTH + inspector::PANEL_WIDTH + PANEL_MARGIN * 3.0;
+    let panel_height: f32 = BUTTON_SPACING * 12.0 + palette.max_height();
+    let height: f32 = BOARD_Y + f32::max(camera.viewport.h, panel_height) + BOARD_X;
+    request_new_screen_size(width, height);
+}
+
+// Draws the RGBA colours of the cells (4 bytes per cell) over the game_board through the camera, it's used for the
+// debug overlays, which change every frame, the game_board itself is drawn by the renderer
+fn draw_board_texture(game_board: &Board, camera: &BoardCamera, pixels: &[u8]) {
+    let board_cells: Texture2D = Texture2D::from_rgba8(game_board.width, game_board.height, pixels);
     board_cells.set_filter(FilterMode::Nearest);
-    draw_texture_ex(
-        &board_cells,
-        5.,
-        60.,
-        WHITE,
-        DrawTextureParams {
-            dest_size: Option::Some(vec2(
-                (game_board.width * CELLSIZE as u16) as f32,
-                (game_board.height * CELLSIZE as u16) as f32,
-            )),
-            source: Option::None,
-            rotation: 0.0,
-            flip_x: false,
-            flip_y: false,
-            pivot: Option::None,
-        },
-    );
+    renderer::draw_texture_on_board(&board_cells, game_board, camera, 1.0);
 }
 
-fn update_board(game_board: &mut Board, selected_material: &mut Material, is_stopped: bool) {
-    let row_count = game_board.height as i32;
-    let col_count: i32 = game_board.width as i32;
+fn update_board(
+    game_board: &mut Board,
+    selected_material: &mut Material,
+    is_stopped: bool,
+    rigid_bodies: bool,
+    brush: &mut Brush,
+    camera: &BoardCamera,
+    conservation: &mut ConservationCheck,
+) {
     if !is_stopped {
-        (0..row_count * col_count).for_each(|count| {
-            let i = count / col_count;
-            let j = count % col_count;
-            game_board.solve_particle(game_board.contents[count as usize].0.phase, i, j);
-        });
+        conservation.step(game_board, get_frame_time(), rigid_bodies);
     }
-    handle_mouse_input(game_board, selected_material, row_count, col_count);
+    handle_mouse_input(game_board, selected_material, brush, camera);
 }
 
 fn handle_mouse_input(
     game_board: &mut Board,
     selected_material: &mut Material,
-    row_count: i32,
-    col_count: i32,
+    brush: &mut Brush,
+    camera: &BoardCamera,
 ) {
-    let btn = MouseButton::Left;
-    let rbtn = MouseButton::Right;
-    if is_mouse_button_down(btn) || is_mouse_button_down(rbtn) {
-        let cursor_position = mouse_position();
-        if cursor_position.0 > CELLSIZE as f32 - 5.0
-            && cursor_position.0 < (CELLSIZE * col_count as u32) as f32
-            && cursor_position.1 > CELLSIZE as f32 + 55.0
-            && cursor_position.1 < (CELLSIZE * row_count as u32) as f32 + 60.0
-        {
-            let x = cursor_position.0 as u32 / CELLSIZE;
-            let y = (cursor_position.1 - 60.0) as u32 / CELLSIZE;
-            let material = if is_mouse_button_down(btn) {
-                *selected_material
-            } else {
-                materials::solid::VOID
-            };
-            game_board.contents[(y * col_count as u32 + x) as usize] = Particle(
-                material,
-                vec2(0.0, 1.0),
-                true,
-                game_board.contents[(y * col_count as u32 + x) as usize].3,
-            );
-        }
-    }
+    // The left mouse button paints with the selected material, the right one erases
+    brush.handle_input(
+        game_board,
+        *selected_material,
+        materials::solid::VOID,
+        camera,
+    );
 }
 
-fn handle_key_inputs(game_board: &mut Board, is_paused: &mut bool) {
-    if is_key_pressed(KeyCode::R) {
-        game_board.create_board(game_board.width, game_board.height);
+fn handle_key_inputs(
+    game_board: &mut Board,
+    is_paused: &mut bool,
+    rigid_bodies: &mut bool,
+    brush: &mut Brush,
+    history: &mut History,
+) {
+    let ctrl: bool = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+    let shift: bool = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+    if is_key_pressed(KeyCode::R) && !ctrl {
+        game_board.clear();
+    }
+    if ctrl && is_key_pressed(KeyCode::Z) && !shift {
+        history.undo(game_board);
+    }
+    if ctrl && (is_key_pressed(KeyCode::Y) || (shift && is_key_pressed(KeyCode::Z))) {
+        history.redo(game_board);
+    }
+    // The snapshots of the simulation take a lot of memory, so they have to be turned on
+    if is_key_pressed(KeyCode::H) {
+        history.snapshots_enabled = !history.snapshots_enabled;
+    }
+    if ctrl && is_key_pressed(KeyCode::Left) {
+        history.rewind(game_board);
     }
     if is_key_pressed(KeyCode::Space) {
         *is_paused = is_paused.not();
     }
+    if is_key_pressed(KeyCode::B) {
+        *rigid_bodies = rigid_bodies.not();
+    }
+    if is_key_pressed(KeyCode::LeftBracket) {
+        brush.radius = (brush.radius - 1).max(0);
+    }
+    if is_key_pressed(KeyCode::RightBracket) {
+        brush.radius = (brush.radius + 1).min(brush::MAX_BRUSH_RADIUS);
+    }
+}
+
+// Draws a button with the label and returns true, if it was clicked in this frame
+fn draw_button(label: &str, x: f32, y: f32, width: f32, height: f32, color: Color) -> bool {
+    draw_rectangle(x, y, width, height, color);
+    draw_text(label, x + 10.0, y + 20.0, 20.0, WHITE);
+    is_mouse_button_pressed(MouseButton::Left) && is_mouse_over_button(x, y, width, height)
 }
 
 fn draw_clear_button(game_board: &mut Board, x: f32, y: f32) {
@@ -163,7 +409,7 @@ fn draw_clear_button(game_board: &mut Board, x: f32, y: f32) {
         && mouse_pos.1 < y + btn_height
         && mouse_pressed
     {
-        game_board.create_board(game_board.width, game_board.height);
+        game_board.clear();
     }
 
     draw_rectangle(x, y, btn_width, btn_height, DARKGRAY);
@@ -195,39 +441,23 @@ pub fn start_pause_button(is_paused: &mut bool, x: f32, y: f32) {
     }
 }
 
-pub fn draw_material_buttons(
-    selected_material: &mut Material,
-    materials: &mut Vec<Material>,
-    x: f32,
-    y: f32,
-) {
+pub fn rigid_bodies_button(rigid_bodies: &mut bool, x: f32, y: f32) {
     let button_width: f32 = 100.0;
     let button_height: f32 = 30.0;
-    for i in 0..materials.len() {
-        let button_color: Color = if selected_material.name != materials[i].name {
-            DARKGRAY
-        } else {
-            DARKBLUE
-        };
-        draw_rectangle(
-            x,
-            y + (40 * i) as f32,
-            button_width,
-            button_height,
-            button_color,
-        );
-        draw_text(
-            &materials[i].name,
-            x + 10.0,
-            (y + 20.0) + (40 * i) as f32,
-            20.0,
-            WHITE,
-        );
-        if is_mouse_button_pressed(MouseButton::Left)
-            && is_mouse_over_button(x, y + (40 * i) as f32, button_width, button_height)
-        {
-            *selected_material = materials[i];
-        }
+    let button_color: Color = if *rigid_bodies { DARKBLUE } else { DARKGRAY };
+    let label: &str = if *rigid_bodies {
+        "Rigid: On"
+    } else {
+        "Rigid: Off"
+    };
+
+    draw_rectangle(x, y, button_width, button_height, button_color);
+    draw_text(label, x + 10.0, y + 20.0, 20.0, WHITE);
+
+    if is_mouse_button_pressed(MouseButton::Left)
+        && is_mouse_over_button(x, y, button_width, button_height)
+    {
+        *rigid_bodies = !*rigid_bodies;
     }
 }
 
@@ -235,10 +465,19 @@ pub fn draw_material_buttons(
 enum Phase {
     Void,
     Solid,
-    Powder { coarseness: f32 }, // Coarseness is the average diameter of a powder particle (between 0 and 1) (in cm), -> , the powder is less stackable it'll flow to the sides like a liquid
-    Liquid { viscosity: f32 }, // Viscosity gives the rate, which the liquid spreads, for e.g. water has a viscosity of 1.0, the bigger the viscosity, the thicker the fluid
-    Gas { viscosity: f32 },    // Viscosity gives the rate, which the gas fills the space
-    Plasma { viscosity: f32 },
+    Powder {
+        coarseness: f32,
+    }, // Coarseness is the average diameter of a powder particle (between 0 and 1) (in cm), -> , the powder is less stackable it'll flow to the sides like a liquid
+    Liquid {
+        viscosity: f32,
+    }, // Viscosity gives the rate, which the liquid spreads, for e.g. water has a viscosity of 1.0, the bigger the viscosity, the thicker the fluid
+    Gas {
+        viscosity: f32,
+    }, // Viscosity gives the rate, which the gas fills the space
+    #[allow(dead_code)] // No plasma materials are implemented yet
+    Plasma {
+        viscosity: f32,
+    },
 }
 
 impl Phase {
@@ -262,36 +501,98 @@ impl Phase {
         };
         returnval
     }
+    // Velocity of a new particle, the powders and the liquids start falling, the other phases start at rest, so a new
+    // gas doesn't drift down
+    pub(crate) fn initial_velocity(&self) -> Vec2 {
+        match self {
+            Phase::Powder { .. } | Phase::Liquid { .. } => vec2(0.0, 1.0),
+            _ => Vec2::ZERO,
+        }
+    }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+// Patterns of the materials, they depend on the position of the cell, so they look like a texture
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Pattern {
+    Plain,
+    Grain, // Wavy horizontal stripes (e.g. wood grain)
+}
+
+#[derive(Copy, Clone)]
 pub struct Material {
-    name: &'static str, // Name of the material
-    mass: f32,          // Mass of a cm^3 volume of the material
-    phase: Phase,       // Phase of the material for the implemented phases check the "Phase" enum
+    name: &'static str,                      // Name of the material
+    mass: f32,                               // Mass of a cm^3 volume of the material
+    phase: Phase, // Phase of the material for the implemented phases check the "Phase" enum
+    behavior: &'static dyn MaterialBehavior, // Movement and interactions of the material, check the "behaviors" module
     durability: i32, // Durability of a material - how much force it needs to disintegrate the material -> higher = more force
     //oxidizer: bool,
     flammability: f32, // Flammability of material -> higher number = more flammable (the flammability is calculated using normal atmospheric conditions (1 bar - 100 000 Pa pressure, 21% oxygen, 78% nitrogen))
     //conductor: bool,
     //resistance: f32,
-    color: Color, // Color of the material
+    anchored: bool, // Anchored solids never move, even when the connected solids fall as rigid bodies
+    temperature: f32, // Temperature of a freshly placed particle of the material (in °C)
+    absorbency: f32, // Moisture gained by soaking up a neighbouring liquid particle (0 - the material doesn't absorb liquids)
+    wet_form: Option<&'static str>, // Name of the material this one turns into when saturated with liquid (e.g. sand -> mud)
+    dry_form: Option<&'static str>, // Name of the material this one turns back into when it dries out (e.g. mud -> sand)
+    emits: Option<devices::Emission>, // Particles spawned by an emitter (None for the other materials), it's set in the emitter panel
+    color_noise: f32, // Variation of the brightness between the cells (0 - every cell has the same colour)
+    emission: f32, // Light emitted by the material in the lighting mode (0 - none, 1 - as bright as the daylight)
+    pattern: Pattern, // Pattern drawn over the cells of the material
+    color: Color,  // Color of the material
 }
 
 #[derive(Copy, Clone)]
-pub struct Particle(Material, Vec2, bool, f32);
+pub struct Particle(Material, Vec2, bool, f32, f32, f32);
 // 0 (Material) - 	Material of the particle
 // 1 (Vec2) - 		Vectors of the particle (x, y)
-// 2 (bool) -       Is it updated?
+// 2 (bool) -       Is it still waiting for its move? (cleared, when the particle moves in the frame)
 // 3 (f32)  -       Random number associated with the cell (for calculating phase behaviour)
+// 4 (f32)  -       Moisture of the particle (0 - dry, SATURATION - soaked)
+// 5 (f32)  -       Temperature of the particle (in °C)
 
 #[derive(Clone)]
 struct Board {
     width: u16,
     height: u16,
     contents: Vec<Particle>,
+    bodies: Vec<rigid::RigidBody>, // Groups of connected solids falling as one object (only in the rigid body mode)
+    edits: Vec<(usize, Particle, Particle)>, // Cells changed by the user since the last commit to the history (index, before, after)
+    tick: u64,                               // Number of simulated frames
+    reactions: Vec<(&'static str, &'static str)>, // Materials changed by the simulation in the last frame (before, after)
+    moves: u64,                         // Particles moved by the solvers in the last frame
+    seed: u64,                          // Seed of the random numbers used by the simulation
+    awake: Vec<u8>, // Frames left until every chunk falls asleep, the sleeping chunks aren't solved
+    actions: Vec<replay::Action>, // Changes made by the user since the last frame, they're recorded into the replays
+    boundaries: boundaries::Boundaries, // Modes of the edges of the game_board (walls, wrapping or open)
+    fields: Vec<forces::ForceField>, // Force fields placed by the user, they push the particles inside them
 }
 
 impl Board {
+    // An empty game_board with a random seed
+    fn new(width: u16, height: u16) -> Board {
+        Board::with_seed(width, height, rand::rand() as u64)
+    }
+    // An empty game_board, the random numbers of the cells are generated from the seed, so the same seed gives the same
+    // game_board
+    fn with_seed(width: u16, height: u16, seed: u64) -> Board {
+        let mut game_board: Board = Board {
+            width: 1,
+            height: 1,
+            contents: vec![],
+            bodies: vec![],
+            edits: vec![],
+            tick: 0,
+            reactions: vec![],
+            moves: 0,
+            seed,
+            awake: vec![],
+            actions: vec![],
+            boundaries: boundaries::Boundaries::default(),
+            fields: vec![],
+        };
+        game_board.create_board(width, height);
+        game_board
+    }
     fn get_width(&self) -> u16 {
         self.width
     }
@@ -302,287 +603,106 @@ impl Board {
         self.width = width;
         self.height = height;
         self.contents = vec![
-            Particle(materials::solid::VOID, vec2(0.0, 0.0), false, 0.0);
-            (&self.width * &self.height) as usize
+            Particle(
+                materials::solid::VOID,
+                vec2(0.0, 0.0),
+                false,
+                0.0,
+                0.0,
+                AMBIENT_TEMPERATURE
+            );
+            self.width as usize * self.height as usize
         ];
-        (0..self.width * self.height).for_each(|count| {
-            self.contents[count as usize].3 = rand::gen_range(0.0, 1.0);
-        });
+        self.bodies.clear();
+        self.wake_all();
+        // The top 24 bits of the hash of the cell fill the mantissa of an f32, so the number is below 1
+        let cells_seed: u64 = chunks::splitmix64(self.seed);
+        for (index, particle) in self.contents.iter_mut().enumerate() {
+            let hash: u64 = chunks::splitmix64(cells_seed ^ index as u64);
+            particle.3 = (hash >> 40) as f32 / (1u64 << 24) as f32;
+        }
+    }
+    // Changes the size of the game_board keeping its contents, the bottom left corner stays in place, so the particles
+    // lying on the floor aren't cut off, the new cells are empty
+    fn resize(&mut self, width: u16, height: u16) {
+        let old: Board = self.clone();
+        self.create_board(width, height);
+        let rows: usize = usize::min(old.height as usize, height as usize);
+        let cols: usize = usize::min(old.width as usize, width as usize);
+        for row in 1..=rows {
+            let old_start = (old.height as usize - row) * old.width as usize;
+            let new_start = (height as usize - row) * width as usize;
+            self.contents[new_start..new_start + cols]
+                .copy_from_slice(&old.contents[old_start..old_start + cols]);
+        }
+        // The force fields move with the bottom rows
+        for field in self.fields.iter_mut() {
+            field.y += height as i32 - old.height as i32;
+        }
+    }
+}
+
+impl chunks::Region<'_> {
+    // Accelerates the particle by the force fields, then moves it by the behaviour of its material
+    fn solve_particle(&mut self, x: i32, y: i32, fields: &[forces::ForceField], frame_time: f32) {
+        if !fields.is_empty() {
+            self.apply_fields(fields, x, y, frame_time);
+        }
+        let Some(particle) = self.get(x, y) else {
+            return;
+        };
+        let behavior: &dyn MaterialBehavior = particle.0.behavior;
+        behavior.update(self, x, y, frame_time);
     }
-    fn solve_particle(&mut self, phase: Phase, i: i32, j: i32) {
-        let row_count: i32 = self.height as i32;
-        let col_count: i32 = self.width as i32;
-        let frame_time = get_frame_time();
-        match phase {
-            Phase::Void => {}
-
-            Phase::Solid => {}
-            /////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-            // POWDER PHYSICS
-            /////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-            Phase::Powder { coarseness: _f32 } => {
-                let cellpos: usize = (i * col_count + j) as usize;
-                // Gravity simulation
-                self.contents[cellpos].1.y += GRAVITY * frame_time;
-                for _k in 0..(self.contents[cellpos].1.y + 1.0) as i32 {
-                    // Falling and checking if there is a particle with a larger mass/density also it marks a particle
-                    // after every pixel it falls, so it doesn't appear instantly at the bottom
-                    if (i + _k) < (row_count)
-                        && self.contents[cellpos].0.mass
-                            > self.contents[((i + _k) * col_count + j) as usize].0.mass
-                        && self.contents[cellpos].2
-                    {
-                        self.contents
-                            .swap(cellpos, ((i + _k) * col_count + j) as usize);
-                        self.contents[((i + _k) * col_count + j) as usize].2 = false;
-                    }
-                    // Checks if the powder particle falls inside bounds, if not, then it corrects it's falling speed
-                    else if (i + _k) >= (row_count) {
-                        self.contents[cellpos].1.y = f32::abs((i - (col_count - 1)) as f32);
-                    }
-                    // Checks, whether there is a solid particle in the path of the falling powder particle, if there
-                    // is, then the falling speed is adjusted, also marks the particle so it doesn't appear on the
-                    // solid particle instantly
-                    else if self.contents[((i + _k) * col_count + j) as usize].0.phase
-                        == Phase::Solid
-                    {
-                        self.contents[cellpos].1.y = f32::abs((i - (i - _k)) as f32);
-                        self.contents[((i + _k) * col_count + j) as usize].2 = false;
-                    }
-                }
-                // We are generating a random number between 0 and 3 (1,2) these numbers correspond the side which
-                // the powder particle falls
-                let rnd: u8 = rand::gen_range(0, 3);
-                // This checks if there is any obstruction to the left side, if not, then the particle falls to the left side
-                if (i < row_count - 1
-                    && j >= 0
-                    && j < col_count - 1
-                    && self.contents[cellpos].2
-                    && (phase.get_coarseness() * 4.0) >= self.contents[cellpos].3)
-                    && (self.contents[(i * col_count + j + 1) as usize].0.mass
-                        < self.contents[cellpos].0.mass
-                        && self.contents[(i * col_count + j + 1) as usize].0.phase != Phase::Solid
-                        && self.contents[((i + 1) * col_count + j + 1) as usize].0.mass
-                            < self.contents[cellpos].0.mass
-                        && rnd == 1)
-                {
-                    self.contents
-                        .swap(cellpos, ((i * col_count) + (j + 1)) as usize);
-                }
-                // This checks if there is any obstruction to the right side, if not, then the particle falls to the left side
-                if (i < row_count - 1
-                    && j < col_count
-                    && j > 0
-                    && self.contents[cellpos].2
-                    && (phase.get_coarseness() * 4.0) >= self.contents[cellpos].3)
-                    && (self.contents[(i * col_count + j - 1) as usize].0.mass
-                        < self.contents[cellpos].0.mass
-                        && self.contents[(i * col_count + j + 1) as usize].0.phase != Phase::Solid
-                        && self.contents[((i + 1) * col_count + j - 1) as usize].0.mass
-                            < self.contents[cellpos].0.mass
-                        && rnd == 2)
-                {
-                    self.contents
-                        .swap(cellpos, ((i * col_count) + (j - 1)) as usize)
-                }
-                // This marks that the particle's position has been calculated
-                self.contents[cellpos].2 = true;
+    // Exchanges heat between the particle and its right and bottom neighbours, so every touching pair is
+    // only calculated once per frame, the empty cells don't conduct heat
+    fn solve_heat(&mut self, x: i32, y: i32, frame_time: f32) {
+        let Some(cellpos) = self.index(x, y) else {
+            return;
+        };
+        if self.contents[cellpos].0.phase == Phase::Void {
+            return;
+        }
+        let transfer: f32 = (HEAT_TRANSFER * frame_time).min(0.5);
+        for direction in [Direction::Right, Direction::Down] {
+            let Some((nx, ny)) = self.neighbour(x, y, direction) else {
+                continue;
+            };
+            let Some(neighbourpos) = self.index(nx, ny) else {
+                continue;
+            };
+            if self.contents[neighbourpos].0.phase == Phase::Void {
+                continue;
             }
-            ///////////////////////////////////////////////////////////////////////////////////////////
-            // LIQUID PHYSICS
-            //////////////////////////////////////////////////////////////////////////////////////////
-            Phase::Liquid { viscosity: _f32 } => {
-                let cellpos: usize = (i * col_count + j) as usize;
-                self.contents[cellpos].1.y += GRAVITY * frame_time;
-                for _k in 0..(self.contents[cellpos].1.y + 1.0) as i32 {
-                    if (i + _k) < (row_count)
-                        && self.contents[cellpos].0.mass
-                            > self.contents[((i + _k) * col_count + j) as usize].0.mass
-                        && self.contents[cellpos].2
-                    {
-                        self.contents
-                            .swap(cellpos, (((i + _k) * col_count) + j) as usize);
-                        self.contents[((i + _k) * col_count + j) as usize].2 = false;
-                    } else if (i + _k) >= (row_count) {
-                        self.contents[cellpos].1.y = f32::abs((i - (row_count - 1)) as f32);
-                    } else if self.contents[((i + _k) * col_count + j) as usize].0.phase
-                        == Phase::Solid
-                    {
-                        self.contents[cellpos].1.y = f32::abs((i - (i - _k)) as f32);
-                        self.contents[((i + _k) * col_count + j) as usize].2 = false;
-                    }
-                }
-                let rnd: i32 = rand::gen_range(-(2.3 * col_count as f32) as i32, col_count);
-                let rnd: i32 = rand::gen_range(-(2.3 * col_count as f32) as i32, col_count);
-                self.contents[cellpos].1.x = rnd as f32 * (1.0 / phase.get_viscosity());
-                for _k in 0..f32::abs(self.contents[cellpos].1.x) as i32 {
-                    if j + (rnd.signum() * _k) < col_count && j + (rnd.signum() * _k) > -1 {
-                        if (j + rnd.signum() * _k) < col_count
-                            && (j + rnd.signum() * _k) > -1
-                            && self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                .0
-                                .phase
-                                == Phase::Void
-                            && self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                .0
-                                .mass
-                                <= self.contents[cellpos].0.mass
-                            && self.contents[cellpos].2
-                        {
-                            self.contents
-                                .swap(cellpos, (i * col_count + j + (rnd.signum() * _k)) as usize);
-                            self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize].2 =
-                                false;
-                        } else if (self.contents
-                            [(i * col_count + j + (rnd.signum() * _k)) as usize]
-                            .0
-                            .mass
-                            >= self.contents[cellpos].0.mass
-                            && std::mem::discriminant(
-                                &self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                    .0
-                                    .phase,
-                            ) == std::mem::discriminant(&Phase::Powder { coarseness: 1.0 }))
-                            || (self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                .0
-                                .phase
-                                == Phase::Solid)
-                        {
-                            break;
-                        }
-                    }
-                }
-                self.contents[cellpos].2 = true;
+            let delta: f32 =
+                (self.contents[neighbourpos].5 - self.contents[cellpos].5) * transfer * 0.5;
+            self.contents[cellpos].5 += delta;
+            self.contents[neighbourpos].5 -= delta;
+            if delta.abs() > chunks::WAKE_TEMPERATURE {
+                self.contents.changed = true;
             }
-
-            Phase::Gas { viscosity: _f32 } => {
-                let cellpos: usize = (i * col_count + j) as usize;
-                let orientation: i32 = rand::gen_range(-2, 2);
-                let mut rnd: i32 = rand::gen_range(-row_count, row_count);
-                self.contents[cellpos].1.y = rnd as f32 * (1.0 / phase.get_viscosity());
-                rnd = rand::gen_range(-col_count, col_count);
-                self.contents[cellpos].1.x = rnd as f32 * (1.0 / phase.get_viscosity());
-                if orientation == -1 {
-                    for _k in 0..f32::abs(self.contents[cellpos].1.y) as i32 {
-                        if i + (rnd.signum() * _k) < row_count && i + (rnd.signum() * _k) > -1 {
-                            if (i + rnd.signum() * _k) < row_count
-                                && (i + rnd.signum() * _k) > -1
-                                && self.contents
-                                    [((i + (rnd.signum() * _k)) * col_count + j) as usize]
-                                    .0
-                                    .phase
-                                    == Phase::Void
-                                && self.contents
-                                    [((i + (rnd.signum() * _k)) * col_count + j) as usize]
-                                    .0
-                                    .mass
-                                    <= self.contents[cellpos].0.mass
-                                && self.contents[cellpos].2
-                            {
-                                self.contents.swap(
-                                    cellpos,
-                                    ((i + (rnd.signum() * _k)) * col_count + j) as usize,
-                                );
-                                self.contents
-                                    [((i + (rnd.signum() * _k)) * col_count + j) as usize]
-                                    .2 = false;
-                            } else if (self.contents
-                                [((i + (rnd.signum() * _k)) * col_count + j) as usize]
-                                .0
-                                .mass
-                                >= self.contents[cellpos].0.mass
-                                && std::mem::discriminant(
-                                    &self.contents[((i + (rnd.signum() as i32 * _k)) * col_count
-                                        + j)
-                                        as usize]
-                                        .0
-                                        .phase,
-                                ) == std::mem::discriminant(&Phase::Liquid { viscosity: 1.0 }))
-                                || (self.contents
-                                    [((i + (rnd.signum() * _k)) * col_count + j) as usize]
-                                    .0
-                                    .mass
-                                    >= self.contents[cellpos].0.mass
-                                    && std::mem::discriminant(
-                                        &self.contents[((i + (rnd.signum() as i32 * _k))
-                                            * col_count
-                                            + j)
-                                            as usize]
-                                            .0
-                                            .phase,
-                                    ) == std::mem::discriminant(&Phase::Powder {
-                                        coarseness: 1.0,
-                                    }))
-                                || (self.contents
-                                    [((i + (rnd.signum() * _k)) * col_count + j) as usize]
-                                    .0
-                                    .phase
-                                    == Phase::Solid)
-                            {
-                                break;
-                            }
-                        }
-                    }
-                } else if orientation == 1 {
-                    for _k in 0..f32::abs(self.contents[cellpos].1.x) as i32 {
-                        if j + (rnd.signum() * _k) < col_count && j + (rnd.signum() * _k) > -1 {
-                            if (j + rnd.signum() * _k) < col_count
-                                && (j + rnd.signum() * _k) > -1
-                                && self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                    .0
-                                    .phase
-                                    == Phase::Void
-                                && self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                    .0
-                                    .mass
-                                    <= self.contents[cellpos].0.mass
-                                && self.contents[cellpos].2
-                            {
-                                self.contents.swap(
-                                    cellpos,
-                                    (i * col_count + j + (rnd.signum() * _k)) as usize,
-                                );
-                                self.contents[(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                    .2 = false;
-                            } else if (self.contents
-                                [(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                .0
-                                .mass
-                                >= self.contents[cellpos].0.mass
-                                && std::mem::discriminant(
-                                    &self.contents
-                                        [(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                        .0
-                                        .phase,
-                                ) == std::mem::discriminant(&Phase::Liquid { viscosity: 1.0 }))
-                                || (self.contents
-                                    [(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                    .0
-                                    .mass
-                                    >= self.contents[cellpos].0.mass
-                                    && std::mem::discriminant(
-                                        &self.contents
-                                            [(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                            .0
-                                            .phase,
-                                    ) == std::mem::discriminant(&Phase::Powder {
-                                        coarseness: 1.0,
-                                    }))
-                                || (self.contents
-                                    [(i * col_count + j + (rnd.signum() * _k)) as usize]
-                                    .0
-                                    .phase
-                                    == Phase::Solid)
-                            {
-                                break;
-                            }
-                        }
-                    }
-                }
-                self.contents[cellpos].2 = true;
+        }
+    }
+    // Calls the contact and heat hooks of the material after the heat exchange, the particle touches a random
+    // neighbour, the empty cells aren't touched
+    fn solve_interactions(&mut self, x: i32, y: i32, frame_time: f32) {
+        let Some(particle) = self.get(x, y) else {
+            return;
+        };
+        if particle.0.phase == Phase::Void {
+            return;
+        }
+        let behavior: &dyn MaterialBehavior = particle.0.behavior;
+        let side: i32 = self.rng.gen_range(0..4);
+        let direction: Direction = Direction::SIDES[side as usize];
+        if let Some((nx, ny)) = self.neighbour(x, y, direction) {
+            if self
+                .get(nx, ny)
+                .is_some_and(|other| other.0.phase != Phase::Void)
+            {
+                behavior.on_contact(self, x, y, nx, ny);
             }
-
-            Phase::Plasma { viscosity: _f32 } => {}
         }
+        behavior.on_heat(self, x, y, frame_time);
     }
 }
@@ -0,0 +1,53 @@
+use macroquad::color::*;
+use macroquad::color_u8;
+
+use crate::neighbourhood::Direction;
+use crate::Phase;
+
+// Materials of this category shown in the material palette
+pub static MATERIALS: [&crate::Material; 2] = [&EMITTER, &DRAIN];
+
+// Spawns particles into the neighbouring cell, the spawned material, the rate and the side are chosen in the emitter
+// panel, so every emitter cell can spawn something else
+pub static EMITTER: crate::Material = crate::Material {
+    name: "Emitter",
+    mass: 2.6,
+    phase: Phase::Solid,
+    behavior: &crate::behaviors::device::Emitter,
+    durability: 200,
+    flammability: 0.0,
+    anchored: true,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: Some(crate::devices::Emission {
+        material: "Water",
+        rate: 10.0,
+        direction: Direction::Down,
+    }),
+    color_noise: 0.0,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
+    color: color_u8!(40, 170, 90, 255),
+};
+
+// Destroys the movable particles (not the solids) touching it
+pub static DRAIN: crate::Material = crate::Material {
+    name: "Drain",
+    mass: 2.6,
+    phase: Phase::Solid,
+    behavior: &crate::behaviors::device::Drain,
+    durability: 200,
+    flammability: 0.0,
+    anchored: true,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: None,
+    color_noise: 0.0,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
+    color: color_u8!(70, 30, 90, 255),
+};
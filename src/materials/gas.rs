@@ -1,5 +1,4 @@
 use macroquad::color::*;
-use macroquad::color_u8;
 
 use crate::Phase;
 
@@ -16,11 +15,24 @@ pub struct Material {
     color: Color, // Color of the material
 }*/
 
+// Materials of this category shown in the material palette
+pub static MATERIALS: [&crate::Material; 1] = [&METHANE];
+
 pub static METHANE: crate::Material = crate::Material {
     name: "Methane",
     mass: 0.657,
     phase: Phase::Gas { viscosity: 1.0 },
+    behavior: &crate::behaviors::gas::Gas,
     durability: 50,
     flammability: 10.0,
+    anchored: false,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: None,
+    color_noise: 0.05,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
     color: YELLOW,
 };
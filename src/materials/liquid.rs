@@ -15,11 +15,43 @@ pub struct Material {
     color: Color, // Color of the material
 }*/
 
+// Materials of this category shown in the material palette
+pub static MATERIALS: [&crate::Material; 2] = [&WATER, &LAVA];
+
 pub static WATER: crate::Material = crate::Material {
     name: "Water",
     mass: 1.0,
     phase: Phase::Liquid { viscosity: 1.0 },
+    behavior: &crate::behaviors::liquid::Liquid,
     durability: 50,
     flammability: 0.0,
+    anchored: false,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: None,
+    color_noise: 0.04,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
     color: BLUE,
 };
+
+pub static LAVA: crate::Material = crate::Material {
+    name: "Lava",
+    mass: 3.1,
+    phase: Phase::Liquid { viscosity: 8.0 },
+    behavior: &crate::behaviors::liquid::Liquid,
+    durability: 50,
+    flammability: 0.0,
+    anchored: false,
+    temperature: 1200.0,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: None,
+    color_noise: 0.15,
+    emission: 1.0,
+    pattern: crate::Pattern::Plain,
+    color: ORANGE,
+};
@@ -1,5 +1,28 @@
+pub(crate) mod device;
 pub(crate) mod gas;
 pub(crate) mod liquid;
 pub(crate) mod plasma;
 pub(crate) mod powder;
 pub(crate) mod solid;
+
+// Categories of the materials shown in the material palette, every category matches one of the modules above
+pub(crate) static CATEGORIES: [(&str, &[&crate::Material]); 6] = [
+    ("Powders", &powder::MATERIALS),
+    ("Liquids", &liquid::MATERIALS),
+    ("Solids", &solid::MATERIALS),
+    ("Gases", &gas::MATERIALS),
+    ("Plasmas", &plasma::MATERIALS),
+    ("Devices", &device::MATERIALS),
+];
+
+// Looks up a material by its name (used for e.g. changing between the wet and dry forms of a material)
+pub(crate) fn find(name: &str) -> Option<crate::Material> {
+    if name == solid::VOID.name {
+        return Some(solid::VOID);
+    }
+    CATEGORIES
+        .iter()
+        .flat_map(|(_, materials)| materials.iter())
+        .find(|material| material.name == name)
+        .map(|material| **material)
+}
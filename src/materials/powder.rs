@@ -16,11 +16,65 @@ pub struct Material {
     color: Color, // Color of the material
 }*/
 
+// Materials of this category shown in the material palette
+pub static MATERIALS: [&crate::Material; 3] = [&SAND, &WET_SAND, &MUD];
+
 pub static SAND: crate::Material = crate::Material {
     name: "Sand",
     mass: 1.682,
     phase: Phase::Powder { coarseness: 0.3 },
+    behavior: &crate::behaviors::powder::Powder,
     durability: 50,
     flammability: 0.0,
+    anchored: false,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.35,
+    wet_form: Some("Wet sand"),
+    dry_form: None,
+    emits: None,
+    color_noise: 0.12,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
     color: color_u8!(203, 189, 147, 255),
 };
+
+// Sand, which soaked up some water, it sticks together and slides less than the dry sand, more water turns it into
+// mud, it dries back into sand when heated
+pub static WET_SAND: crate::Material = crate::Material {
+    name: "Wet sand",
+    mass: 1.8,
+    phase: Phase::Powder { coarseness: 0.15 },
+    behavior: &crate::behaviors::powder::Powder,
+    durability: 50,
+    flammability: 0.0,
+    anchored: false,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.35,
+    wet_form: Some("Mud"),
+    dry_form: Some("Sand"),
+    emits: None,
+    color_noise: 0.12,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
+    color: color_u8!(158, 140, 103, 255),
+};
+
+// Sand saturated with water, it barely slides and dries back into wet sand when heated
+pub static MUD: crate::Material = crate::Material {
+    name: "Mud",
+    mass: 1.9,
+    phase: Phase::Powder { coarseness: 0.05 },
+    behavior: &crate::behaviors::powder::Powder,
+    durability: 50,
+    flammability: 0.0,
+    anchored: false,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: Some("Wet sand"),
+    emits: None,
+    color_noise: 0.1,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
+    color: color_u8!(94, 72, 50, 255),
+};
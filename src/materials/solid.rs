@@ -16,21 +16,64 @@ pub struct Material {
     color: Color, // Color of the material
 }*/
 
+// Materials of this category shown in the material palette (the void is only used for erasing)
+pub static MATERIALS: [&crate::Material; 2] = [&WOOD, &STONE];
+
 // This is the only material that has the Void phase, but it's inside the solid materials, because it acts as such, but other materials ignore it
 pub static VOID: crate::Material = crate::Material {
     name: "Void",
     mass: 0.0,
     phase: Phase::Void,
+    behavior: &crate::behaviors::Inert,
     durability: -1,
     flammability: 0.0,
+    anchored: false,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: None,
+    color_noise: 0.0,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
     color: color_u8!(0, 0, 0, 100),
 };
 
 pub static WOOD: crate::Material = crate::Material {
     name: "Wood",
-    mass: 2.0,
+    mass: 0.6,
     phase: Phase::Solid,
+    behavior: &crate::behaviors::Inert,
     durability: 40,
     flammability: 10.0,
+    anchored: false,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: None,
+    color_noise: 0.06,
+    emission: 0.0,
+    pattern: crate::Pattern::Grain,
     color: BROWN,
 };
+
+// Stone doesn't fall in the rigid body mode, the solids connected to it stay in place too
+pub static STONE: crate::Material = crate::Material {
+    name: "Stone",
+    mass: 2.6,
+    phase: Phase::Solid,
+    behavior: &crate::behaviors::Inert,
+    durability: 200,
+    flammability: 0.0,
+    anchored: true,
+    temperature: crate::AMBIENT_TEMPERATURE,
+    absorbency: 0.0,
+    wet_form: None,
+    dry_form: None,
+    emits: None,
+    color_noise: 0.15,
+    emission: 0.0,
+    pattern: crate::Pattern::Plain,
+    color: GRAY,
+};
@@ -0,0 +1,389 @@
+use crate::boundaries::Boundaries;
+use crate::chunks::Region;
+use crate::{Board, Particle};
+
+// Directions of the neighbouring cells, the rows (y) grow downwards
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub(crate) const SIDES: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+    pub(crate) const DIAGONALS: [Direction; 4] = [
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::DownLeft,
+        Direction::DownRight,
+    ];
+    // Change of the column and the row after one step in the direction
+    pub(crate) fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+    // The direction of the step (dx, dy), only the signs of the numbers matter, no step is None
+    pub(crate) fn from_offset(dx: i32, dy: i32) -> Option<Direction> {
+        Direction::SIDES
+            .into_iter()
+            .chain(Direction::DIAGONALS)
+            .find(|direction| direction.offset() == (dx.signum(), dy.signum()))
+    }
+}
+
+// Access to the cells by their column x and row y, instead of calculating the indices of the contents by hand. The
+// cells behind a wrapping edge are the cells on the opposite side, the cells behind the other edges act as walls:
+// they can't be read, changed or swapped into, so the solvers don't need their own checks of the edges and the corners
+pub(crate) trait Neighbourhood {
+    // Number of the columns and the rows of the game_board
+    fn size(&self) -> (i32, i32);
+    fn particle(&self, index: usize) -> &Particle;
+    fn particle_mut(&mut self, index: usize) -> &mut Particle;
+    fn swap_indices(&mut self, a: usize, b: usize);
+    // Whether the cell can be reached from here, a region only holds the rows around its chunk
+    fn holds(&self, _index: usize) -> bool {
+        true
+    }
+    // Modes of the edges, every edge is a wall by default
+    fn boundaries(&self) -> Boundaries {
+        Boundaries::default()
+    }
+
+    // The cell at the column x and row y after going through a wrapping edge, None behind the other edges. It only
+    // looks at the edges of the game_board, so it tells the edge apart from the end of a region
+    fn resolve(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (width, height) = self.size();
+        self.boundaries().resolve(x, y, width, height)
+    }
+    // Index of the cell in the contents, None outside of the game_board and outside of the rows held by a region
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let (x, y) = self.resolve(x, y)?;
+        let index: usize = (y * self.size().0 + x) as usize;
+        self.holds(index).then_some(index)
+    }
+    // Column and row of the cell with the index
+    fn position(&self, index: usize) -> (i32, i32) {
+        let width: i32 = self.size().0;
+        (index as i32 % width, index as i32 / width)
+    }
+    fn get(&self, x: i32, y: i32) -> Option<&Particle> {
+        self.index(x, y).map(|index| self.particle(index))
+    }
+    fn get_mut(&mut self, x: i32, y: i32) -> Option<&mut Particle> {
+        let index: usize = self.index(x, y)?;
+        Some(self.particle_mut(index))
+    }
+    // Swaps the particles of the two cells, returns false (and changes nothing), if one of them is outside
+    fn swap(&mut self, a: (i32, i32), b: (i32, i32)) -> bool {
+        match (self.index(a.0, a.1), self.index(b.0, b.1)) {
+            (Some(a), Some(b)) => {
+                self.swap_indices(a, b);
+                true
+            }
+            _ => false,
+        }
+    }
+    // The neighbouring cell in the direction, None at the edge (except the wrapping ones)
+    fn neighbour(&self, x: i32, y: i32, direction: Direction) -> Option<(i32, i32)> {
+        let (dx, dy) = direction.offset();
+        let (nx, ny) = self.resolve(x + dx, y + dy)?;
+        self.index(nx, ny).map(|_| (nx, ny))
+    }
+    // The cells above, below, left and right of the cell (in this order), the ones outside are left out
+    fn neighbours(&self, x: i32, y: i32) -> impl Iterator<Item = (i32, i32)> + '_ {
+        Direction::SIDES
+            .into_iter()
+            .filter_map(move |direction| self.neighbour(x, y, direction))
+    }
+    // The cells in the direction from the cell (without the cell itself), until the edge of the game_board, through
+    // a wrapping edge it goes on up to the cell itself. It doesn't borrow the neighbourhood, so the cells can be
+    // changed while walking along it
+    fn ray(&self, x: i32, y: i32, direction: Direction) -> Ray {
+        let (width, height) = self.size();
+        let (dx, dy) = direction.offset();
+        let length: i32 = match (dx, dy) {
+            (_, 0) => width,
+            (0, _) => height,
+            _ => width.min(height),
+        };
+        Ray {
+            x,
+            y,
+            dx,
+            dy,
+            width,
+            height,
+            boundaries: self.boundaries(),
+            left: length - 1,
+        }
+    }
+}
+
+// Iterator over the cells in a direction, created by Neighbourhood::ray
+pub(crate) struct Ray {
+    x: i32, // The last returned cell
+    y: i32,
+    dx: i32, // Step between two cells
+    dy: i32,
+    width: i32, // Size of the game_board
+    height: i32,
+    boundaries: Boundaries,
+    left: i32, // Number of the cells left, so a ray through the wrapping edges doesn't go around forever
+}
+
+impl Iterator for Ray {
+    type Item = (i32, i32);
+    fn next(&mut self) -> Option<(i32, i32)> {
+        if self.left <= 0 {
+            return None;
+        }
+        let (x, y) =
+            self.boundaries
+                .resolve(self.x + self.dx, self.y + self.dy, self.width, self.height)?;
+        (self.x, self.y, self.left) = (x, y, self.left - 1);
+        Some((x, y))
+    }
+}
+
+// The rigid bodies don't go through the edges, so the whole game_board treats every edge as a wall
+impl Neighbourhood for Board {
+    fn size(&self) -> (i32, i32) {
+        (self.width as i32, self.height as i32)
+    }
+    fn particle(&self, index: usize) -> &Particle {
+        &self.contents[index]
+    }
+    fn particle_mut(&mut self, index: usize) -> &mut Particle {
+        &mut self.contents[index]
+    }
+    fn swap_indices(&mut self, a: usize, b: usize) {
+        self.contents.swap(a, b);
+    }
+}
+
+impl Neighbourhood for Region<'_> {
+    fn size(&self) -> (i32, i32) {
+        (self.width as i32, self.height as i32)
+    }
+    fn particle(&self, index: usize) -> &Particle {
+        &self.contents[index]
+    }
+    fn particle_mut(&mut self, index: usize) -> &mut Particle {
+        &mut self.contents[index]
+    }
+    fn swap_indices(&mut self, a: usize, b: usize) {
+        self.contents.swap(a, b);
+    }
+    fn holds(&self, index: usize) -> bool {
+        self.contents.holds(index)
+    }
+    fn boundaries(&self) -> Boundaries {
+        self.boundaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::rand::rngs::StdRng;
+    use ::rand::SeedableRng;
+
+    use super::*;
+    use crate::boundaries::EdgeMode;
+    use crate::chunks::Cells;
+
+    const WIDTH: u16 = 6;
+    const HEIGHT: u16 = 8;
+
+    // A game_board, where the random number of every cell is its index, so the cells can be told apart after a swap
+    fn numbered_board() -> Board {
+        let mut game_board: Board = Board::new(WIDTH, HEIGHT);
+        for (index, particle) in game_board.contents.iter_mut().enumerate() {
+            particle.3 = index as f32;
+        }
+        game_board
+    }
+
+    // A region holding the rows first_row..end_row of the game_board
+    fn region(
+        game_board: &mut Board,
+        first_row: usize,
+        end_row: usize,
+        boundaries: Boundaries,
+    ) -> Region<'_> {
+        let width: usize = WIDTH as usize;
+        Region {
+            width: WIDTH,
+            height: HEIGHT,
+            contents: Cells::new(
+                first_row * width,
+                &mut game_board.contents[first_row * width..end_row * width],
+            ),
+            reactions: vec![],
+            rng: StdRng::seed_from_u64(0),
+            boundaries,
+        }
+    }
+
+    fn wrapping_sides() -> Boundaries {
+        Boundaries {
+            left: EdgeMode::Wrap,
+            right: EdgeMode::Wrap,
+            ..Boundaries::default()
+        }
+    }
+
+    // Every corner of a walled neighbourhood, which holds all rows: the cells behind it can't be reached, the ones
+    // inside can
+    fn check_walled_corners(cells: &mut impl Neighbourhood) {
+        let (width, height) = cells.size();
+        let corners = [
+            ((0, 0), Direction::UpLeft, Direction::DownRight),
+            ((width - 1, 0), Direction::UpRight, Direction::DownLeft),
+            ((0, height - 1), Direction::DownLeft, Direction::UpRight),
+            (
+                (width - 1, height - 1),
+                Direction::DownRight,
+                Direction::UpLeft,
+            ),
+        ];
+        for ((x, y), outwards, inwards) in corners {
+            let (dx, dy) = outwards.offset();
+            let index: usize = (y * width + x) as usize;
+            assert_eq!(cells.resolve(x, y), Some((x, y)));
+            assert_eq!(cells.index(x, y), Some(index));
+            assert_eq!(cells.resolve(x + dx, y + dy), None);
+            assert_eq!(cells.index(x + dx, y + dy), None);
+            assert_eq!(cells.index(x + dx, y), None);
+            assert_eq!(cells.index(x, y + dy), None);
+            assert_eq!(cells.neighbour(x, y, outwards), None);
+            let (ix, iy) = inwards.offset();
+            assert_eq!(cells.neighbour(x, y, inwards), Some((x + ix, y + iy)));
+            assert_eq!(cells.neighbours(x, y).count(), 2);
+            // The ray along the edge stops at the other corner, the one outwards is empty
+            let along = Direction::from_offset(-dx, 0).unwrap();
+            assert_eq!(cells.ray(x, y, along).count(), width as usize - 1);
+            assert_eq!(cells.ray(x, y, along).last(), Some((width - 1 - x, y)));
+            assert_eq!(cells.ray(x, y, outwards).next(), None);
+            // Nothing is swapped with a cell behind the wall
+            assert!(!cells.swap((x, y), (x + dx, y + dy)));
+            assert_eq!(cells.particle(index).3, index as f32);
+        }
+        assert!(cells.swap((0, 0), (width - 1, height - 1)));
+        assert_eq!(cells.get(0, 0).unwrap().3, (width * height - 1) as f32);
+        assert_eq!(cells.get(width - 1, height - 1).unwrap().3, 0.0);
+    }
+
+    #[test]
+    fn board_corners_are_walls() {
+        let mut game_board: Board = numbered_board();
+        game_board.boundaries = wrapping_sides();
+        // The game_board itself ignores its modes of the edges
+        check_walled_corners(&mut game_board);
+    }
+
+    #[test]
+    fn region_corners_are_walls() {
+        let mut game_board: Board = numbered_board();
+        let mut region: Region = region(&mut game_board, 0, HEIGHT as usize, Boundaries::default());
+        check_walled_corners(&mut region);
+    }
+
+    #[test]
+    fn region_wraps_through_the_sides() {
+        let mut game_board: Board = numbered_board();
+        let mut region: Region = region(&mut game_board, 0, HEIGHT as usize, wrapping_sides());
+        let (width, height) = (WIDTH as i32, HEIGHT as i32);
+        for y in [0, height - 1] {
+            assert_eq!(region.resolve(-1, y), Some((width - 1, y)));
+            assert_eq!(region.resolve(width, y), Some((0, y)));
+            assert_eq!(region.index(-1, y), Some((y * width + width - 1) as usize));
+            assert_eq!(
+                region.neighbour(0, y, Direction::Left),
+                Some((width - 1, y))
+            );
+            assert_eq!(
+                region.neighbour(width - 1, y, Direction::Right),
+                Some((0, y))
+            );
+            assert_eq!(region.neighbours(0, y).count(), 3);
+        }
+        // The top and the bottom are still walls, also in the corners
+        assert_eq!(region.neighbour(0, 0, Direction::UpLeft), None);
+        assert_eq!(region.neighbour(0, height - 1, Direction::DownLeft), None);
+        assert_eq!(
+            region.neighbour(0, height - 1, Direction::UpLeft),
+            Some((width - 1, height - 2))
+        );
+        // The ray goes around once and stops before the starting cell
+        let ray: Vec<(i32, i32)> = region.ray(1, 2, Direction::Left).collect();
+        assert_eq!(ray.len(), width as usize - 1);
+        assert_eq!(ray[..2], [(0, 2), (width - 1, 2)]);
+        assert_eq!(ray.last(), Some(&(2, 2)));
+        // A swap through the edge swaps with the cell on the opposite side
+        assert!(region.swap((0, 3), (-1, 3)));
+        assert_eq!(region.get(width - 1, 3).unwrap().3, (3 * width) as f32);
+        assert_eq!(region.get(0, 3).unwrap().3, (4 * width - 1) as f32);
+    }
+
+    #[test]
+    fn region_hides_the_rows_outside_of_its_window() {
+        let mut game_board: Board = numbered_board();
+        let boundaries = Boundaries {
+            top: EdgeMode::Wrap,
+            bottom: EdgeMode::Wrap,
+            ..Boundaries::default()
+        };
+        // Only the rows 2 to 5 are held
+        let mut region: Region = region(&mut game_board, 2, 6, boundaries);
+        let (width, height) = (WIDTH as i32, HEIGHT as i32);
+        for x in [0, width - 1] {
+            // The cells are on the game_board, so they resolve, but the region can't reach them
+            for y in [1, 6] {
+                assert_eq!(region.resolve(x, y), Some((x, y)));
+                assert_eq!(region.index(x, y), None);
+                assert!(region.get(x, y).is_none());
+            }
+            assert_eq!(region.resolve(x, -1), Some((x, height - 1)));
+            assert_eq!(region.index(x, -1), None);
+            assert_eq!(region.index(x, 2), Some((2 * width + x) as usize));
+            assert_eq!(region.neighbour(x, 2, Direction::Up), None);
+            assert_eq!(region.neighbour(x, 5, Direction::Down), None);
+            assert_eq!(region.neighbour(x, 3, Direction::Up), Some((x, 2)));
+            assert_eq!(region.neighbours(x, 2).count(), 2);
+            // Nothing is swapped with the cells outside of the window
+            assert!(!region.swap((x, 2), (x, 1)));
+            assert!(!region.swap((x, 5), (x, 6)));
+            assert_eq!(region.get(x, 2).unwrap().3, (2 * width + x) as f32);
+        }
+        // The ray doesn't know the window, but every cell behind it can't be read
+        let ray: Vec<(i32, i32)> = region.ray(0, 3, Direction::Up).collect();
+        assert_eq!(ray.len(), height as usize - 1);
+        assert_eq!(
+            ray.iter()
+                .filter(|(x, y)| region.get(*x, *y).is_some())
+                .count(),
+            3
+        );
+        assert!(region.swap((0, 2), (width - 1, 5)));
+        assert_eq!(region.get(0, 2).unwrap().3, (6 * width - 1) as f32);
+    }
+}
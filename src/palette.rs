@@ -0,0 +1,182 @@
+use macroquad::prelude::*;
+
+use crate::{is_mouse_over_button, materials, Material, Phase, BUTTON_HEIGHT, BUTTON_WIDTH};
+
+const ROW_HEIGHT: f32 = 24.0; // Height of a material button or a category header
+const ROW_SPACING: f32 = 28.0;
+const TOOLTIP_WIDTH: f32 = 210.0;
+
+// The material buttons grouped by their categories, which can be filtered with the search box
+#[derive(Default)]
+pub(crate) struct Palette {
+    search: String,               // Text typed into the search box
+    pub(crate) focused: bool, // The search box receives the pressed keys instead of the key shortcuts
+    collapsed: Vec<&'static str>, // Categories, whose materials are hidden
+    hovered: Option<Material>, // Material under the cursor, its properties are shown in a tooltip
+}
+
+impl Palette {
+    // Height of the palette with every category expanded and nothing filtered out
+    pub(crate) fn max_height(&self) -> f32 {
+        let rows: usize = materials::CATEGORIES
+            .iter()
+            .map(|(_, materials)| materials.len() + 1)
+            .sum();
+        BUTTON_HEIGHT + 10.0 + ROW_SPACING * rows as f32
+    }
+    fn matches(&self, category: &str, material: &Material) -> bool {
+        let search = self.search.to_lowercase();
+        material.name.to_lowercase().contains(&search) || category.to_lowercase().contains(&search)
+    }
+    pub(crate) fn draw(&mut self, selected_material: &mut Material, x: f32, y: f32) {
+        self.handle_typing();
+        // Search box
+        let clicked = is_mouse_button_pressed(MouseButton::Left);
+        if clicked {
+            self.focused = is_mouse_over_button(x, y, BUTTON_WIDTH, BUTTON_HEIGHT);
+        }
+        draw_rectangle(x, y, BUTTON_WIDTH, BUTTON_HEIGHT, DARKGRAY);
+        if self.focused {
+            draw_rectangle_lines(x, y, BUTTON_WIDTH, BUTTON_HEIGHT, 2.0, WHITE);
+        }
+        let (text, color) = if self.search.is_empty() && !self.focused {
+            ("Search...".to_string(), LIGHTGRAY)
+        } else if self.focused {
+            (format!("{}_", self.search), WHITE)
+        } else {
+            (self.search.clone(), WHITE)
+        };
+        draw_text(&text, x + 5.0, y + 20.0, 20.0, color);
+
+        self.hovered = None;
+        let mut row_y = y + BUTTON_HEIGHT + 10.0;
+        for (category, materials) in materials::CATEGORIES.iter() {
+            let shown: Vec<&Material> = materials
+                .iter()
+                .copied()
+                .filter(|material| self.matches(category, material))
+                .collect();
+            if shown.is_empty() {
+                continue;
+            }
+            // Clicking the header of a category hides or shows its materials
+            let is_collapsed = self.collapsed.contains(category) && self.search.is_empty();
+            draw_text(
+                &format!("{} {category}", if is_collapsed { "+" } else { "-" }),
+                x,
+                row_y + 17.0,
+                20.0,
+                WHITE,
+            );
+            if clicked && is_mouse_over_button(x, row_y, BUTTON_WIDTH, ROW_HEIGHT) {
+                if is_collapsed {
+                    self.collapsed.retain(|name| name != category);
+                } else {
+                    self.collapsed.push(category);
+                }
+            }
+            row_y += ROW_SPACING;
+            if is_collapsed {
+                continue;
+            }
+            for material in shown {
+                let button_color: Color = if selected_material.name != material.name {
+                    DARKGRAY
+                } else {
+                    DARKBLUE
+                };
+                draw_rectangle(x, row_y, BUTTON_WIDTH, ROW_HEIGHT, button_color);
+                draw_rectangle(x + 4.0, row_y + 6.0, 12.0, 12.0, material.color);
+                draw_text(material.name, x + 22.0, row_y + 17.0, 20.0, WHITE);
+                if is_mouse_over_button(x, row_y, BUTTON_WIDTH, ROW_HEIGHT) {
+                    self.hovered = Some(*material);
+                    if clicked {
+                        *selected_material = *material;
+                    }
+                }
+                row_y += ROW_SPACING;
+            }
+        }
+    }
+    // Typing into the focused search box, the characters are read every frame, so they don't pile up until the search
+    // box gets the focus
+    fn handle_typing(&mut self) {
+        while let Some(character) = get_char_pressed() {
+            if self.focused && !character.is_control() {
+                self.search.push(character);
+            }
+        }
+        if !self.focused {
+            return;
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.search.pop();
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.search.clear();
+            self.focused = false;
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            self.focused = false;
+        }
+    }
+    // Shows the properties of the material under the cursor, it's drawn after everything else, so it stays on top
+    pub(crate) fn draw_tooltip(&self) {
+        let Some(material) = self.hovered else {
+            return;
+        };
+        let phase: String = match material.phase {
+            Phase::Void => "Void".to_string(),
+            Phase::Solid => if material.anchored {
+                "Solid (anchored)"
+            } else {
+                "Solid"
+            }
+            .to_string(),
+            Phase::Powder { coarseness } => format!("Powder (coarseness {coarseness})"),
+            Phase::Liquid { viscosity } => format!("Liquid (viscosity {viscosity})"),
+            Phase::Gas { viscosity } => format!("Gas (viscosity {viscosity})"),
+            Phase::Plasma { viscosity } => format!("Plasma (viscosity {viscosity})"),
+        };
+        let mut lines: Vec<String> = vec![
+            material.name.to_string(),
+            phase,
+            format!("Mass: {}", material.mass),
+            format!("Durability: {}", material.durability),
+            format!("Flammability: {}", material.flammability),
+            format!("Temperature: {} °C", material.temperature),
+        ];
+        if material.absorbency > 0.0 {
+            lines.push(format!("Absorbency: {}", material.absorbency));
+        }
+        if material.emission > 0.0 {
+            lines.push(format!("Light: {}", material.emission));
+        }
+        if let Some(wet_form) = material.wet_form {
+            lines.push(format!("Turns into {wet_form} when wet"));
+        }
+        if let Some(dry_form) = material.dry_form {
+            lines.push(format!("Dries into {dry_form}"));
+        }
+        if let Some(emission) = material.emits {
+            lines.push(format!(
+                "Spawns {} ({}/s, {:?})",
+                emission.material, emission.rate, emission.direction
+            ));
+        }
+        let (mouse_x, mouse_y) = mouse_position();
+        let height = lines.len() as f32 * 18.0 + 8.0;
+        // The tooltip is shown on the left side of the cursor, if it wouldn't fit on the screen
+        let x = if mouse_x + 15.0 + TOOLTIP_WIDTH > screen_width() {
+            mouse_x - 15.0 - TOOLTIP_WIDTH
+        } else {
+            mouse_x + 15.0
+        };
+        let y = mouse_y.min(screen_height() - height);
+        draw_rectangle(x, y, TOOLTIP_WIDTH, height, Color::new(0.0, 0.0, 0.0, 0.85));
+        draw_rectangle_lines(x, y, TOOLTIP_WIDTH, height, 1.0, material.color);
+        for (row, line) in lines.iter().enumerate() {
+            draw_text(line, x + 5.0, y + 18.0 * (row + 1) as f32, 18.0, WHITE);
+        }
+    }
+}
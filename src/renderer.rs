@@ -0,0 +1,166 @@
+use macroquad::prelude::*;
+
+use crate::camera::BoardCamera;
+use crate::chunks::CHUNK_SIZE;
+use crate::{Board, Particle, Pattern, SATURATION};
+
+const WETNESS_DARKENING: f32 = 0.35; // Brightness lost by a soaked particle
+const GRAIN_FREQUENCY: f32 = 1.3; // Number of stripes of the grain per row (in radians)
+const GRAIN_STRENGTH: f32 = 0.12; // Brightness difference between the stripes of the grain
+const IGNITION_TEMPERATURE: f32 = 300.0; // Above this temperature the flammable particles look burning (in °C)
+pub(crate) const GLOW_TEMPERATURE: f32 = 500.0; // Above this temperature the particles start glowing (in °C)
+pub(crate) const GLOW_RANGE: f32 = 1000.0; // Temperature above the glow temperature, at which the particles glow the most
+const FIRE_COLOR: Vec3 = vec3(1.0, 0.55, 0.1);
+const EMBER_COLOR: Vec3 = vec3(0.45, 0.08, 0.02);
+
+// Keeps the colours of the cells in a texture, only the cells of the awake chunks can change, so only they're
+// recoloured and only the part of the texture around the changed cells is uploaded
+#[derive(Default)]
+pub(crate) struct Renderer {
+    texture: Option<Texture2D>, // Created with the first frame, because it needs the window
+    size: (u16, u16),           // Size of the game_board drawn into the texture
+    pixels: Vec<u8>,            // RGBA colours of the cells (4 bytes per cell)
+    upload: Vec<u8>, // Changed part of the pixels, it's kept, so it isn't allocated in every frame
+}
+
+impl Renderer {
+    pub(crate) fn draw(&mut self, game_board: &Board, camera: &BoardCamera) {
+        self.update(game_board);
+        if let Some(texture) = &self.texture {
+            draw_texture_on_board(texture, game_board, camera, 1.0);
+        }
+    }
+    fn update(&mut self, game_board: &Board) {
+        let (width, height) = (game_board.width, game_board.height);
+        // The whole texture is created again, when the size of the game_board changed
+        if self.texture.is_none() || self.size != (width, height) {
+            self.pixels = board_pixels(game_board);
+            let texture = Texture2D::from_rgba8(width, height, &self.pixels);
+            texture.set_filter(FilterMode::Nearest);
+            self.texture = Some(texture);
+            self.size = (width, height);
+            return;
+        }
+        // Recolouring the awake chunks, the changed cells are collected into a rectangle (left, top, right, bottom)
+        let (width, height) = (width as usize, height as usize);
+        let chunk_cols: usize = width.div_ceil(CHUNK_SIZE);
+        let mut changed: Option<(usize, usize, usize, usize)> = None;
+        for (chunk, awake) in game_board.awake.iter().enumerate() {
+            if *awake == 0 {
+                continue;
+            }
+            let (chunk_row, chunk_col) = (chunk / chunk_cols, chunk % chunk_cols);
+            for i in chunk_row * CHUNK_SIZE..((chunk_row + 1) * CHUNK_SIZE).min(height) {
+                for j in chunk_col * CHUNK_SIZE..((chunk_col + 1) * CHUNK_SIZE).min(width) {
+                    let index: usize = i * width + j;
+                    let color: [u8; 4] = particle_color(&game_board.contents[index], j, i);
+                    if self.pixels[index * 4..index * 4 + 4] != color {
+                        self.pixels[index * 4..index * 4 + 4].copy_from_slice(&color);
+                        changed = Some(match changed {
+                            Some((left, top, right, bottom)) => {
+                                (left.min(j), top.min(i), right.max(j), bottom.max(i))
+                            }
+                            None => (j, i, j, i),
+                        });
+                    }
+                }
+            }
+        }
+        let (Some((left, top, right, bottom)), Some(texture)) = (changed, &self.texture) else {
+            return;
+        };
+        self.upload.clear();
+        for i in top..=bottom {
+            self.upload.extend_from_slice(
+                &self.pixels[(i * width + left) * 4..(i * width + right + 1) * 4],
+            );
+        }
+        let image = Image {
+            bytes: std::mem::take(&mut self.upload),
+            width: (right - left + 1) as u16,
+            height: (bottom - top + 1) as u16,
+        };
+        texture.update_part(
+            &image,
+            left as i32,
+            top as i32,
+            image.width as i32,
+            image.height as i32,
+        );
+        self.upload = image.bytes;
+    }
+}
+
+// RGBA colours of every cell of the game_board (4 bytes per cell), it doesn't need a window
+pub(crate) fn board_pixels(game_board: &Board) -> Vec<u8> {
+    let width = game_board.width as usize;
+    game_board
+        .contents
+        .iter()
+        .enumerate()
+        .flat_map(|(index, particle)| particle_color(particle, index % width, index / width))
+        .collect()
+}
+
+// Colour of the particle in the cell (x - column, y - row), it varies with the random number of the particle, the
+// pattern of the material and the state of the particle (wet, burning or hot)
+fn particle_color(particle: &Particle, x: usize, y: usize) -> [u8; 4] {
+    let material = &particle.0;
+    let mut brightness: f32 = 1.0 + (particle.3 - 0.5) * 2.0 * material.color_noise;
+    if material.pattern == Pattern::Grain {
+        let wave: f32 = (y as f32 * GRAIN_FREQUENCY + (x as f32 * 0.15).sin() * 2.0).sin();
+        brightness += wave * GRAIN_STRENGTH;
+    }
+    brightness *= 1.0 - WETNESS_DARKENING * (particle.4 / SATURATION).min(1.0);
+    let mut color: Vec3 = vec3(material.color.r, material.color.g, material.color.b) * brightness;
+    // The burning particles are a mix of flames and embers, the simulation doesn't burn them yet, they only look so
+    if material.flammability > 0.0 && particle.5 > IGNITION_TEMPERATURE {
+        let fire: Vec3 = FIRE_COLOR.lerp(EMBER_COLOR, particle.3);
+        let burning: f32 =
+            (particle.5 - IGNITION_TEMPERATURE) / (GLOW_TEMPERATURE - IGNITION_TEMPERATURE);
+        color = color.lerp(fire, burning.clamp(0.0, 1.0) * 0.8);
+    }
+    // The particles heated above their own temperature glow from red to yellow, so e.g. lava doesn't change its colour
+    let glow: f32 =
+        ((particle.5 - GLOW_TEMPERATURE.max(material.temperature)) / GLOW_RANGE).clamp(0.0, 1.0);
+    if glow > 0.0 {
+        color = color.lerp(vec3(1.0, 0.3 + 0.6 * glow, 0.1 * glow), glow * 0.8);
+    }
+    [
+        (color.x.clamp(0.0, 1.0) * 255.0) as u8,
+        (color.y.clamp(0.0, 1.0) * 255.0) as u8,
+        (color.z.clamp(0.0, 1.0) * 255.0) as u8,
+        (material.color.a * 255.0) as u8,
+    ]
+}
+
+// Draws the texture over the game_board through the camera, a texel of the texture covers a square of cells
+pub(crate) fn draw_texture_on_board(
+    texture: &Texture2D,
+    game_board: &Board,
+    camera: &BoardCamera,
+    cells_per_texel: f32,
+) {
+    // Only the part of the game_board inside the viewport of the camera is drawn
+    let visible: Rect = camera.visible_cells(game_board.width, game_board.height);
+    let screen_position: Vec2 = camera.board_to_screen(visible.point());
+    draw_texture_ex(
+        texture,
+        screen_position.x,
+        screen_position.y,
+        WHITE,
+        DrawTextureParams {
+            dest_size: Option::Some(visible.size() * camera.zoom),
+            source: Option::Some(Rect::new(
+                visible.x / cells_per_texel,
+                visible.y / cells_per_texel,
+                visible.w / cells_per_texel,
+                visible.h / cells_per_texel,
+            )),
+            rotation: 0.0,
+            flip_x: false,
+            flip_y: false,
+            pivot: Option::None,
+        },
+    );
+}
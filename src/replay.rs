@@ -0,0 +1,710 @@
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+
+use crate::boundaries::Boundaries;
+use crate::brush::BrushShape;
+use crate::devices::{self, Emission};
+use crate::forces::{ForceField, FIELD_BYTES};
+use crate::scene::{self, Reader};
+use crate::settings::Args;
+use crate::{capture, materials, Board, Material, Particle};
+
+pub(crate) const REPLAY_FILE: &str = "board.replay"; // File saved, when the recording started with F8 stops, and played with F9
+const MAGIC: &[u8; 7] = b"RRREPLY"; // Start of every replay file
+const VERSION: u8 = b'2'; // Version of the format, the byte after MAGIC, the older versions can't be read
+
+// A change of the game_board made by the user, the game_board collects them, so they can be recorded into a replay
+#[derive(Clone)]
+pub(crate) enum Action {
+    Line {
+        from: (i32, i32),
+        to: (i32, i32),
+        material: Material,
+        shape: BrushShape,
+        radius: i32,
+    },
+    Rectangle {
+        from: (i32, i32),
+        to: (i32, i32),
+        material: Material,
+    },
+    Fill {
+        x: i32,
+        y: i32,
+        material: Material,
+    },
+    Clear,
+    Cells(Vec<(usize, Particle)>), // Cells restored by undoing or redoing an edit
+    AddField(ForceField),
+    RemoveFields {
+        x: i32,
+        y: i32,
+    }, // Removes the force fields covering the cell
+}
+
+// An entry of the replay log, the simulated frames are entries too, so the replay is played back tick for tick
+#[derive(Clone)]
+enum Event {
+    Step { frame_time: f32, rigid_bodies: bool },
+    Action(Action),
+    Select(Material), // The user selected another material in the palette
+    Pause(bool),      // The user paused or started the simulation
+}
+
+impl Board {
+    // Repeats an action of the user, e.g. while playing back a replay, the action is collected again
+    pub(crate) fn apply(&mut self, action: &Action) {
+        match action {
+            Action::Line {
+                from,
+                to,
+                material,
+                shape,
+                radius,
+            } => self.paint_line(*from, *to, *material, *shape, *radius),
+            Action::Rectangle { from, to, material } => self.paint_rectangle(*from, *to, *material),
+            Action::Fill { x, y, material } => self.flood_fill(*x, *y, *material),
+            Action::Clear => self.clear(),
+            Action::Cells(cells) => {
+                for (index, particle) in cells {
+                    self.contents[*index] = *particle;
+                    self.wake(*index);
+                }
+                self.actions.push(action.clone());
+            }
+            Action::AddField(field) => self.add_field(*field),
+            Action::RemoveFields { x, y } => self.remove_fields(*x, *y),
+        }
+    }
+}
+
+// The game_board at the start of the recording, the timestamped events and the state of the game_board at the end, so
+// the playback can check, that it reached the same result
+struct ReplayFile {
+    start: Vec<u8>,            // The game_board at the start encoded as a scene file
+    final_tick: u64,           // Tick of the game_board at the end of the recording
+    checksum: u64,             // Checksum of the game_board at the end of the recording
+    events: Vec<(u32, Event)>, // Events and the number of the frame since the start of the recording
+}
+
+// FNV-1a hash of the encoded game_board, it's the same on every computer, unlike the hashers of the standard library
+fn checksum(game_board: &Board) -> u64 {
+    scene::encode(game_board)
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+impl ReplayFile {
+    // MAGIC, VERSION, the length of the start and the start, the final tick and checksum, then the events until the
+    // end of the file (number of the frame, type of the event and its values), all numbers are little endian
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(self.start.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.start);
+        bytes.extend_from_slice(&self.final_tick.to_le_bytes());
+        bytes.extend_from_slice(&self.checksum.to_le_bytes());
+        for (frame, event) in &self.events {
+            bytes.extend_from_slice(&frame.to_le_bytes());
+            encode_event(&mut bytes, event);
+        }
+        bytes
+    }
+    fn decode(bytes: &[u8]) -> Result<ReplayFile, String> {
+        let bytes: &[u8] = scene::strip_header(bytes, MAGIC, VERSION)?;
+        let mut reader = Reader::new(bytes);
+        let length = u32::from_le_bytes(take(&mut reader)?);
+        let start = reader
+            .slice(length as usize)
+            .ok_or_else(read_error)?
+            .to_vec();
+        let final_tick = u64::from_le_bytes(take(&mut reader)?);
+        let checksum = u64::from_le_bytes(take(&mut reader)?);
+        let mut events: Vec<(u32, Event)> = vec![];
+        while reader.remaining() > 0 {
+            let frame = u32::from_le_bytes(take(&mut reader)?);
+            events.push((frame, decode_event(&mut reader)?));
+        }
+        Ok(ReplayFile {
+            start,
+            final_tick,
+            checksum,
+            events,
+        })
+    }
+    fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.encode())
+            .map_err(|error| format!("Couldn't save \"{path}\": {error}"))
+    }
+    fn load(path: &str) -> Result<ReplayFile, String> {
+        let bytes: Vec<u8> =
+            std::fs::read(path).map_err(|error| format!("Couldn't read \"{path}\": {error}"))?;
+        ReplayFile::decode(&bytes)
+            .map_err(|problem| format!("\"{path}\" isn't a valid replay file: {problem}"))
+    }
+}
+
+fn read_error() -> String {
+    "the file ends too early".to_string()
+}
+
+fn take<const N: usize>(reader: &mut Reader) -> Result<[u8; N], String> {
+    reader.take().ok_or_else(read_error)
+}
+
+fn encode_event(bytes: &mut Vec<u8>, event: &Event) {
+    let numbers = |bytes: &mut Vec<u8>, numbers: &[i32]| {
+        for number in numbers {
+            bytes.extend_from_slice(&number.to_le_bytes());
+        }
+    };
+    match event {
+        Event::Step {
+            frame_time,
+            rigid_bodies,
+        } => {
+            bytes.push(0);
+            bytes.extend_from_slice(&frame_time.to_le_bytes());
+            bytes.push(*rigid_bodies as u8);
+        }
+        Event::Action(Action::Line {
+            from,
+            to,
+            material,
+            shape,
+            radius,
+        }) => {
+            bytes.push(1);
+            numbers(bytes, &[from.0, from.1, to.0, to.1, *radius]);
+            bytes.push((*shape == BrushShape::Square) as u8);
+            encode_material(bytes, material);
+        }
+        Event::Action(Action::Rectangle { from, to, material }) => {
+            bytes.push(2);
+            numbers(bytes, &[from.0, from.1, to.0, to.1]);
+            encode_material(bytes, material);
+        }
+        Event::Action(Action::Fill { x, y, material }) => {
+            bytes.push(3);
+            numbers(bytes, &[*x, *y]);
+            encode_material(bytes, material);
+        }
+        Event::Action(Action::Clear) => bytes.push(4),
+        Event::Action(Action::Cells(cells)) => {
+            bytes.push(5);
+            bytes.extend_from_slice(&(cells.len() as u32).to_le_bytes());
+            for (index, particle) in cells {
+                bytes.extend_from_slice(&(*index as u32).to_le_bytes());
+                encode_material(bytes, &particle.0);
+                for value in [
+                    particle.1.x,
+                    particle.1.y,
+                    particle.3,
+                    particle.4,
+                    particle.5,
+                ] {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                bytes.push(particle.2 as u8);
+            }
+        }
+        Event::Select(material) => {
+            bytes.push(6);
+            encode_material(bytes, material);
+        }
+        Event::Pause(is_paused) => bytes.push(7 + *is_paused as u8),
+        Event::Action(Action::AddField(field)) => {
+            bytes.push(9);
+            bytes.extend_from_slice(&field.to_bytes());
+        }
+        Event::Action(Action::RemoveFields { x, y }) => {
+            bytes.push(10);
+            numbers(bytes, &[*x, *y]);
+        }
+    }
+}
+
+fn decode_event(reader: &mut Reader) -> Result<Event, String> {
+    let point = |reader: &mut Reader| -> Result<(i32, i32), String> {
+        Ok((
+            i32::from_le_bytes(take(reader)?),
+            i32::from_le_bytes(take(reader)?),
+        ))
+    };
+    let [kind] = take(reader)?;
+    let event = match kind {
+        0 => Event::Step {
+            frame_time: f32::from_le_bytes(take(reader)?),
+            rigid_bodies: take::<1>(reader)? != [0],
+        },
+        1 => {
+            let (from, to) = (point(reader)?, point(reader)?);
+            let radius = i32::from_le_bytes(take(reader)?);
+            let shape = if take::<1>(reader)? == [0] {
+                BrushShape::Circle
+            } else {
+                BrushShape::Square
+            };
+            Event::Action(Action::Line {
+                from,
+                to,
+                material: decode_material(reader)?,
+                shape,
+                radius,
+            })
+        }
+        2 => Event::Action(Action::Rectangle {
+            from: point(reader)?,
+            to: point(reader)?,
+            material: decode_material(reader)?,
+        }),
+        3 => {
+            let (x, y) = point(reader)?;
+            Event::Action(Action::Fill {
+                x,
+                y,
+                material: decode_material(reader)?,
+            })
+        }
+        4 => Event::Action(Action::Clear),
+        5 => {
+            let count = u32::from_le_bytes(take(reader)?);
+            let mut cells: Vec<(usize, Particle)> = vec![];
+            for _ in 0..count {
+                let index = u32::from_le_bytes(take(reader)?) as usize;
+                let material = decode_material(reader)?;
+                let mut values = [0.0; 5];
+                for value in values.iter_mut() {
+                    *value = f32::from_le_bytes(take(reader)?);
+                }
+                let updated = take::<1>(reader)? != [0];
+                cells.push((
+                    index,
+                    Particle(
+                        material,
+                        vec2(values[0], values[1]),
+                        updated,
+                        values[2],
+                        values[3],
+                        values[4],
+                    ),
+                ));
+            }
+            Event::Action(Action::Cells(cells))
+        }
+        6 => Event::Select(decode_material(reader)?),
+        7 | 8 => Event::Pause(kind == 8),
+        9 => Event::Action(Action::AddField(ForceField::from_bytes(take::<
+            FIELD_BYTES,
+        >(reader)?)?)),
+        10 => {
+            let (x, y) = point(reader)?;
+            Event::Action(Action::RemoveFields { x, y })
+        }
+        _ => return Err(format!("unknown event {kind}")),
+    };
+    Ok(event)
+}
+
+// The name of the material and its emission (see devices::encode_emission)
+fn encode_material(bytes: &mut Vec<u8>, material: &Material) {
+    bytes.push(material.name.len() as u8);
+    bytes.extend_from_slice(material.name.as_bytes());
+    devices::encode_emission(bytes, material.emits);
+}
+
+fn decode_material(reader: &mut Reader) -> Result<Material, String> {
+    let [length] = take(reader)?;
+    let name = reader.slice(length as usize).ok_or_else(read_error)?;
+    let name = String::from_utf8_lossy(name);
+    let mut material: Material =
+        materials::find(&name).ok_or_else(|| format!("unknown material \"{name}\""))?;
+    material.emits = devices::decode_emission(reader)?;
+    Ok(material)
+}
+
+// A replay being recorded, the events are collected at the start of every frame
+struct Recording {
+    file: ReplayFile,
+    frame: u32, // Number of the frames since the start of the recording
+    tick: u64,  // Tick of the game_board, when the events were collected last time
+    board: (u16, u16, u64, Boundaries), // Size, seed and edges of the recorded game_board, a changed one isn't recorded
+    step: (f32, bool), // Frame time and rigid body mode of the step simulated in this frame
+    selected: (&'static str, Option<Emission>), // Name and emission of the material selected in the palette
+    is_paused: bool,
+}
+
+impl Recording {
+    // The rigid bodies are dropped and every chunk is woken, the bodies and the sleeping chunks aren't saved with the
+    // game_board, so the recorded game_board and the played back one continue the same way. All random numbers come
+    // from the seed of the game_board, which is saved with the start
+    fn start(game_board: &mut Board, selected: &Material, is_paused: bool) -> Recording {
+        game_board.bodies.clear();
+        game_board.wake_all();
+        game_board.actions.clear();
+        Recording {
+            file: ReplayFile {
+                start: scene::encode(game_board),
+                final_tick: 0,
+                checksum: 0,
+                events: vec![(0, Event::Select(*selected)), (0, Event::Pause(is_paused))],
+            },
+            frame: 0,
+            tick: game_board.tick,
+            board: (
+                game_board.width,
+                game_board.height,
+                game_board.seed,
+                game_board.boundaries,
+            ),
+            step: (0.0, false),
+            selected: (selected.name, selected.emits),
+            is_paused,
+        }
+    }
+    // Adds the step simulated since the last call and the actions of the user to the events, returns false, if the
+    // game_board was rewound or replaced, so it can't be recorded any more
+    fn collect(&mut self, game_board: &mut Board) -> bool {
+        if (
+            game_board.width,
+            game_board.height,
+            game_board.seed,
+            game_board.boundaries,
+        ) != self.board
+            || !(self.tick..=self.tick + 1).contains(&game_board.tick)
+        {
+            return false;
+        }
+        if game_board.tick == self.tick + 1 {
+            let (frame_time, rigid_bodies) = self.step;
+            self.file.events.push((
+                self.frame,
+                Event::Step {
+                    frame_time,
+                    rigid_bodies,
+                },
+            ));
+            self.tick += 1;
+        }
+        for action in game_board.actions.drain(..) {
+            self.file.events.push((self.frame, Event::Action(action)));
+        }
+        true
+    }
+}
+
+// A replay being played back
+struct Player {
+    events: VecDeque<(u32, Event)>,
+    frame: u32, // Number of the frames since the start of the playback
+    final_tick: u64,
+    checksum: u64,
+}
+
+impl Player {
+    // Replaces the game_board with the start of the replay
+    fn start(file: ReplayFile) -> Result<(Player, Board), String> {
+        let game_board: Board = scene::decode(&file.start).map_err(|problem| {
+            format!("The replay starts with an invalid game_board: {problem}")
+        })?;
+        let player = Player {
+            events: file.events.into(),
+            frame: 0,
+            final_tick: file.final_tick,
+            checksum: file.checksum,
+        };
+        Ok((player, game_board))
+    }
+    // Plays the events of the next frame, the simulated steps run with the recorded frame time
+    fn play_frame(
+        &mut self,
+        game_board: &mut Board,
+        selected: &mut Material,
+        is_paused: &mut bool,
+        rigid: &mut bool,
+    ) {
+        let current: u32 = self.frame;
+        while self
+            .events
+            .front()
+            .is_some_and(|(frame, _)| *frame <= current)
+        {
+            let Some((_, event)) = self.events.pop_front() else {
+                break;
+            };
+            match event {
+                Event::Step {
+                    frame_time,
+                    rigid_bodies,
+                } => {
+                    *rigid = rigid_bodies;
+                    game_board.step(frame_time, rigid_bodies);
+                }
+                Event::Action(action) => game_board.apply(&action),
+                Event::Select(material) => *selected = material,
+                Event::Pause(paused) => *is_paused = paused,
+            }
+        }
+        game_board.actions.clear();
+        self.frame += 1;
+    }
+    fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+    // Checks, that the played back game_board ended the same way as the recorded one
+    fn verify(&self, game_board: &Board) -> Result<(), String> {
+        if game_board.tick != self.final_tick || checksum(game_board) != self.checksum {
+            return Err(format!(
+                "The replay ended differently than the recording (at tick {}, the recording ended at tick {})",
+                game_board.tick, self.final_tick
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Recording with the F8 key and playing back with the F9 key, the replays are saved into REPLAY_FILE
+#[derive(Default)]
+pub(crate) struct Replay {
+    recording: Option<Recording>,
+    player: Option<Player>,
+    message: String, // The state of the recording or the result of the playback
+}
+
+impl Replay {
+    pub(crate) fn is_playing(&self) -> bool {
+        self.player.is_some()
+    }
+    // Returns true, when the game_board was replaced by the start of a replay, so the size of the window can be changed
+    pub(crate) fn handle_input(
+        &mut self,
+        game_board: &mut Board,
+        selected: &Material,
+        is_paused: bool,
+    ) -> bool {
+        if is_key_pressed(KeyCode::F8) && !self.is_playing() {
+            match self.recording.take() {
+                Some(mut recording) => {
+                    self.message = if recording.collect(game_board) {
+                        recording.file.final_tick = game_board.tick;
+                        recording.file.checksum = checksum(game_board);
+                        match recording.file.save(REPLAY_FILE) {
+                            Ok(()) => format!("Saved the replay into \"{REPLAY_FILE}\""),
+                            Err(error) => error,
+                        }
+                    } else {
+                        "The recording was cancelled, the game_board was replaced".to_string()
+                    };
+                }
+                None => {
+                    self.recording = Some(Recording::start(game_board, selected, is_paused));
+                    self.message = "Recording a replay (F8 stops it)".to_string();
+                }
+            }
+        }
+        if is_key_pressed(KeyCode::F9) && self.recording.is_none() {
+            if self.player.take().is_some() {
+                self.message = "The playback was stopped".to_string();
+                return false;
+            }
+            match ReplayFile::load(REPLAY_FILE).and_then(Player::start) {
+                Ok((player, start)) => {
+                    *game_board = start;
+                    self.player = Some(player);
+                    self.message = format!("Playing \"{REPLAY_FILE}\" (F9 stops it)");
+                    return true;
+                }
+                Err(error) => self.message = error,
+            }
+        }
+        false
+    }
+    // Called at the start of every frame, before the game_board is simulated and edited
+    pub(crate) fn record(
+        &mut self,
+        game_board: &mut Board,
+        selected: &Material,
+        is_paused: bool,
+        rigid_bodies: bool,
+    ) {
+        let Some(recording) = &mut self.recording else {
+            game_board.actions.clear();
+            return;
+        };
+        recording.frame += 1;
+        if !recording.collect(game_board) {
+            self.recording = None;
+            self.message = "The recording was cancelled, the game_board was replaced".to_string();
+            return;
+        }
+        if (selected.name, selected.emits) != recording.selected {
+            recording.selected = (selected.name, selected.emits);
+            recording
+                .file
+                .events
+                .push((recording.frame, Event::Select(*selected)));
+        }
+        if is_paused != recording.is_paused {
+            recording.is_paused = is_paused;
+            recording
+                .file
+                .events
+                .push((recording.frame, Event::Pause(is_paused)));
+        }
+        recording.step = (get_frame_time(), rigid_bodies);
+    }
+    // Plays the next frame of the replay instead of the simulation and the user input
+    pub(crate) fn play(
+        &mut self,
+        game_board: &mut Board,
+        selected: &mut Material,
+        is_paused: &mut bool,
+        rigid_bodies: &mut bool,
+    ) {
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        player.play_frame(game_board, selected, is_paused, rigid_bodies);
+        if player.is_finished() {
+            self.message = match player.verify(game_board) {
+                Ok(()) => "The replay matches the recording".to_string(),
+                Err(error) => error,
+            };
+            self.player = None;
+        }
+    }
+    pub(crate) fn draw(&self, x: f32, y: f32) {
+        draw_text(&self.message, x, y, 20.0, WHITE);
+    }
+}
+
+// The "replay" command, it plays a replay file back without a window and checks, that it ends the same way as the
+// recording, the final game_board can be saved as a scene file and an image
+pub(crate) fn run_replay(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let (mut path, mut out, mut png): (Option<String>, Option<String>, Option<String>) =
+        (None, None, None);
+    let mut scale: u32 = 1;
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next()? {
+        match arg.as_str() {
+            "--out" => out = Some(args.value()?),
+            "--png" => png = Some(args.value()?),
+            "--scale" => scale = args.number::<u32>()?.clamp(1, 64),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option \"{arg}\"")),
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("Unexpected argument \"{arg}\"")),
+        }
+    }
+    let path: String = path.ok_or("Missing the replay file")?;
+    let (mut player, mut game_board) = Player::start(ReplayFile::load(&path)?)?;
+    let (mut selected, mut is_paused, mut rigid_bodies) = (materials::solid::VOID, false, false);
+    while !player.is_finished() {
+        player.play_frame(
+            &mut game_board,
+            &mut selected,
+            &mut is_paused,
+            &mut rigid_bodies,
+        );
+    }
+    if let Some(out) = &out {
+        scene::save(&game_board, out)?;
+    }
+    if let Some(png) = &png {
+        capture::save_png(&game_board, png, scale)?;
+    }
+    player.verify(&game_board)?;
+    println!(
+        "The replay matches the recording after {} frames (tick {})",
+        player.frame, game_board.tick
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forces::FieldKind;
+
+    const FRAME_TIME: f32 = 1.0 / 60.0;
+    const FRAMES: u32 = 60;
+
+    // The actions of the user in the frame, every kind of action is made once
+    fn edit(game_board: &mut Board, frame: u32) {
+        match frame {
+            1 => game_board.paint_line(
+                (5, 5),
+                (40, 10),
+                materials::powder::SAND,
+                BrushShape::Circle,
+                2,
+            ),
+            5 => game_board.add_field(ForceField {
+                kind: FieldKind::Vortex,
+                x: 30,
+                y: 30,
+                radius: 10,
+                strength: 40.0,
+            }),
+            10 => game_board.flood_fill(0, 49, materials::liquid::WATER),
+            20 => game_board.clear(),
+            25 => game_board.paint_line(
+                (10, 40),
+                (50, 40),
+                materials::solid::WOOD,
+                BrushShape::Square,
+                1,
+            ),
+            30 => {
+                let mut cells: Vec<(usize, Particle)> = vec![];
+                for index in [100, 101, 102] {
+                    let mut particle: Particle = game_board.contents[index];
+                    particle.0 = materials::liquid::LAVA;
+                    particle.5 = 1200.0;
+                    cells.push((index, particle));
+                }
+                game_board.apply(&Action::Cells(cells));
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn a_recorded_replay_plays_back_the_same_way() {
+        let mut game_board: Board = Board::with_seed(60, 50, 3);
+        let mut recording = Recording::start(&mut game_board, &materials::solid::VOID, false);
+        // The same order as in the window: the events of the last frame are collected, then the game_board is
+        // simulated and the user edits it
+        for frame in 0..FRAMES {
+            recording.frame += 1;
+            assert!(recording.collect(&mut game_board));
+            let rigid_bodies: bool = frame >= FRAMES / 2;
+            recording.step = (FRAME_TIME, rigid_bodies);
+            game_board.step(FRAME_TIME, rigid_bodies);
+            edit(&mut game_board, frame);
+        }
+        recording.frame += 1;
+        assert!(recording.collect(&mut game_board));
+        recording.file.final_tick = game_board.tick;
+        recording.file.checksum = checksum(&game_board);
+
+        let file: ReplayFile = ReplayFile::decode(&recording.file.encode()).unwrap();
+        assert_eq!(file.events.len(), recording.file.events.len());
+        let (mut player, mut played) = Player::start(file).unwrap();
+        let (mut selected, mut is_paused, mut rigid_bodies) = (materials::solid::WOOD, true, false);
+        while !player.is_finished() {
+            player.play_frame(
+                &mut played,
+                &mut selected,
+                &mut is_paused,
+                &mut rigid_bodies,
+            );
+        }
+        assert_eq!(played.tick, FRAMES as u64);
+        assert_eq!(selected.name, materials::solid::VOID.name);
+        assert!(!is_paused && rigid_bodies);
+        player.verify(&played).unwrap();
+    }
+}
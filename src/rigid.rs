@@ -0,0 +1,546 @@
+use std::collections::{BTreeSet, HashSet};
+
+use ::rand::rngs::StdRng;
+use ::rand::Rng;
+use macroquad::prelude::*;
+
+use crate::chunks::{chunk_rng, MAX_REACH};
+use crate::neighbourhood::{Direction, Neighbourhood};
+use crate::{materials, Board, Particle, Phase, AMBIENT_TEMPERATURE, GRAVITY};
+
+const IMPACT_STRESS: f32 = 10.0; // Durability needed to survive an impact of 1 cell/frame speed
+const LIQUID_DRAG: f32 = 3.0; // Velocity lost per second by a body moving through a liquid
+const ANGULAR_DAMPING: f32 = 0.98; // Fraction of the angular velocity kept every frame
+const MAX_SUBSTEPS: i32 = 32; // Maximum number of collision checks per body per frame
+const RIGID_BODY_PASS: u64 = 2; // Pass of the random numbers of the bodies, the chunks use the passes 0 and 1
+
+// A group of connected solid particles, which falls, rotates and collides as a unit
+#[derive(Clone)]
+pub(crate) struct RigidBody {
+    cells: Vec<(Vec2, Particle)>, // Offset of the cell from the centre of mass (in cells) and the particle inside it
+    occupied: Vec<(usize, usize)>, // Indices of the game_board cells covered by the body and the body cell drawn there
+    position: Vec2, // Centre of mass of the body on the game_board (x - column, y - row)
+    velocity: Vec2, // Velocity of the body (in cells/frame, like the velocity of the particles)
+    angle: f32,     // Rotation of the body (in radians)
+    angular_velocity: f32, // Angular velocity of the body (in radians/frame)
+}
+
+impl RigidBody {
+    fn mass(&self) -> f32 {
+        self.cells.iter().map(|(_, particle)| particle.0.mass).sum()
+    }
+    // Moment of inertia around the centre of mass
+    fn inertia(&self) -> f32 {
+        self.cells
+            .iter()
+            .map(|(offset, particle)| particle.0.mass * offset.length_squared())
+            .sum::<f32>()
+            .max(1.0)
+    }
+    fn radius(&self) -> f32 {
+        self.cells
+            .iter()
+            .map(|(offset, _)| offset.length())
+            .fold(0.0, f32::max)
+    }
+}
+
+fn rotate(offset: Vec2, angle: f32) -> Vec2 {
+    Vec2::from_angle(angle).rotate(offset)
+}
+
+fn is_liquid(phase: Phase) -> bool {
+    matches!(phase, Phase::Liquid { .. })
+}
+
+// Void, liquids, gases and plasmas are pushed out of the way of the bodies, powders and solids stop them
+fn is_displaceable(phase: Phase) -> bool {
+    !matches!(phase, Phase::Solid | Phase::Powder { .. })
+}
+
+impl Board {
+    // The bodies draw their random numbers from the seed of the game_board like the chunks, so the headless runs and
+    // the replays with bodies repeat
+    pub(crate) fn solve_rigid_bodies(&mut self, frame_time: f32) {
+        let mut rng: StdRng = chunk_rng(self.seed, self.tick, RIGID_BODY_PASS, 0, 0);
+        self.sync_bodies();
+        self.detect_bodies();
+        for b in (0..self.bodies.len()).rev() {
+            let impact = self.move_body(b, frame_time, &mut rng);
+            if impact > 0.0 {
+                self.break_body(b, impact);
+            }
+        }
+    }
+    // Returns the owner body of every cell of the game_board
+    fn body_owners(&self) -> Vec<Option<usize>> {
+        let mut owners: Vec<Option<usize>> = vec![None; self.contents.len()];
+        for (b, body) in self.bodies.iter().enumerate() {
+            for (index, _) in &body.occupied {
+                owners[*index] = Some(b);
+            }
+        }
+        owners
+    }
+    // Copies the state (e.g. temperature) of the particles from the game_board into the bodies, the cells, which were
+    // erased or replaced since the last frame are removed from the body
+    fn sync_bodies(&mut self) {
+        for b in (0..self.bodies.len()).rev() {
+            let mut lost_cell = false;
+            for (index, cell) in self.bodies[b].occupied.clone() {
+                if self.contents[index].0.name == self.bodies[b].cells[cell].1 .0.name {
+                    self.bodies[b].cells[cell].1 = self.contents[index];
+                } else {
+                    lost_cell = true;
+                }
+            }
+            if lost_cell {
+                self.break_cells(b, &[]);
+            }
+        }
+    }
+    // Groups the connected solid particles, which aren't part of a body yet, into new bodies, the groups touching an
+    // anchored solid stay in place. A new solid wakes its chunk, so only the awake chunks are searched for the groups
+    fn detect_bodies(&mut self) {
+        let owners = self.body_owners();
+        let mut visited: Vec<bool> = vec![false; self.contents.len()];
+        for start in self.awake_rows().into_iter().flatten() {
+            if visited[start] || !self.is_loose_solid(start, &owners) {
+                continue;
+            }
+            let mut group: Vec<usize> = vec![];
+            let mut anchored = false;
+            let mut stack: Vec<usize> = vec![start];
+            visited[start] = true;
+            while let Some(index) = stack.pop() {
+                group.push(index);
+                let (x, y) = self.position(index);
+                let neighbours = self.neighbours(x, y);
+                for neighbour in neighbours.filter_map(|(nx, ny)| self.index(nx, ny)) {
+                    if self.contents[neighbour].0.phase == Phase::Solid
+                        && self.contents[neighbour].0.anchored
+                    {
+                        anchored = true;
+                    }
+                    if !visited[neighbour] && self.is_loose_solid(neighbour, &owners) {
+                        visited[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            if !anchored {
+                let body = self.create_body(&group, Vec2::ZERO, 0.0);
+                self.bodies.push(body);
+            }
+        }
+    }
+    fn is_loose_solid(&self, index: usize, owners: &[Option<usize>]) -> bool {
+        self.contents[index].0.phase == Phase::Solid
+            && !self.contents[index].0.anchored
+            && owners[index].is_none()
+    }
+    // Creates a body from the particles of the game_board at the given indices
+    fn create_body(&self, indices: &[usize], velocity: Vec2, angular_velocity: f32) -> RigidBody {
+        let col_count = self.width as usize;
+        let positions: Vec<Vec2> = indices
+            .iter()
+            .map(|index| vec2((index % col_count) as f32, (index / col_count) as f32))
+            .collect();
+        let total_mass: f32 = indices
+            .iter()
+            .map(|index| self.contents[*index].0.mass)
+            .sum::<f32>()
+            .max(f32::EPSILON);
+        let position: Vec2 = indices
+            .iter()
+            .zip(&positions)
+            .map(|(index, position)| *position * self.contents[*index].0.mass)
+            .sum::<Vec2>()
+            / total_mass;
+        RigidBody {
+            cells: indices
+                .iter()
+                .zip(&positions)
+                .map(|(index, cell_position)| (*cell_position - position, self.contents[*index]))
+                .collect(),
+            occupied: indices
+                .iter()
+                .enumerate()
+                .map(|(k, index)| (*index, k))
+                .collect(),
+            position,
+            velocity,
+            angle: 0.0,
+            angular_velocity,
+        }
+    }
+    // Returns the cells covered by the body at the given position and angle or None, if it collides with something
+    fn rasterize(&self, b: usize, position: Vec2, angle: f32) -> Option<Vec<(usize, usize)>> {
+        let body = &self.bodies[b];
+        let own: HashSet<usize> = body.occupied.iter().map(|(index, _)| *index).collect();
+        let mut covered: HashSet<usize> = HashSet::new();
+        let mut occupied: Vec<(usize, usize)> = vec![];
+        for (k, (offset, _)) in body.cells.iter().enumerate() {
+            let cell_position = (position + rotate(*offset, angle)).round();
+            if cell_position.x < 0.0
+                || cell_position.y < 0.0
+                || cell_position.x >= self.width as f32
+                || cell_position.y >= self.height as f32
+            {
+                return None;
+            }
+            let index = (cell_position.y as usize) * self.width as usize + cell_position.x as usize;
+            if !own.contains(&index) && !is_displaceable(self.contents[index].0.phase) {
+                return None;
+            }
+            // Two cells can be rounded into the same cell (e.g. when the body is turned), the second one is moved into
+            // a free neighbouring cell, so no particle of the body gets lost
+            let index = if covered.contains(&index) {
+                self.free_neighbour(index, &own, &covered)?
+            } else {
+                index
+            };
+            covered.insert(index);
+            occupied.push((index, k));
+        }
+        Some(occupied)
+    }
+    // A neighbouring cell, which isn't covered by the body yet and can be taken by it
+    fn free_neighbour(
+        &self,
+        index: usize,
+        own: &HashSet<usize>,
+        covered: &HashSet<usize>,
+    ) -> Option<usize> {
+        let (x, y) = self.position(index);
+        let diagonals = Direction::DIAGONALS
+            .into_iter()
+            .filter_map(|direction| self.neighbour(x, y, direction));
+        self.neighbours(x, y)
+            .chain(diagonals)
+            .filter_map(|(nx, ny)| self.index(nx, ny))
+            .find(|neighbour| {
+                !covered.contains(neighbour)
+                    && (own.contains(neighbour)
+                        || is_displaceable(self.contents[*neighbour].0.phase))
+            })
+    }
+    // Looks for a liquid next to the cell on its left, its right and above it, skipping the cells of the body itself,
+    // returns the mass of the heaviest liquid found. A liquid below the cell doesn't count, the body only displaces the
+    // liquid with the cells under its waterline
+    fn submerging_liquid(&self, index: usize, own: &HashSet<usize>) -> Option<f32> {
+        let (x, y) = self.position(index);
+        [Direction::Up, Direction::Left, Direction::Right]
+            .into_iter()
+            .filter_map(|direction| {
+                let neighbour: usize = self
+                    .ray(x, y, direction)
+                    .filter_map(|(nx, ny)| self.index(nx, ny))
+                    .find(|neighbour| !own.contains(neighbour))?;
+                let material = &self.contents[neighbour].0;
+                is_liquid(material.phase).then_some(material.mass)
+            })
+            .reduce(f32::max)
+    }
+    // Finds the horizontal range of the cells of the body, which rest on an obstacle
+    fn support_range(&self, b: usize, own: &HashSet<usize>) -> Option<(Vec2, Vec2)> {
+        let col_count = self.width as usize;
+        let mut range: Option<(Vec2, Vec2)> = None;
+        for (index, _) in &self.bodies[b].occupied {
+            let below = index + col_count;
+            let resting = below >= self.contents.len()
+                || (!own.contains(&below) && !is_displaceable(self.contents[below].0.phase));
+            if resting {
+                let point = vec2((index % col_count) as f32, (index / col_count) as f32 + 0.5);
+                range = Some(match range {
+                    None => (point, point),
+                    Some((left, right)) => (
+                        if point.x < left.x { point } else { left },
+                        if point.x > right.x { point } else { right },
+                    ),
+                });
+            }
+        }
+        range
+    }
+    // Applies gravity, buoyancy and tipping over the edges to the body, then moves it as far as it can go, returns the
+    // speed of the impact, if the body hit something
+    fn move_body(&mut self, b: usize, frame_time: f32, rng: &mut StdRng) -> f32 {
+        let own: HashSet<usize> = self.bodies[b]
+            .occupied
+            .iter()
+            .map(|(index, _)| *index)
+            .collect();
+        let mass = self.bodies[b].mass();
+        let inertia = self.bodies[b].inertia();
+        let mut force = vec2(0.0, mass * GRAVITY);
+        let mut torque: f32 = 0.0;
+        let mut submerged: usize = 0;
+        for (index, cell) in &self.bodies[b].occupied {
+            if let Some(liquid_mass) = self.submerging_liquid(*index, &own) {
+                // The buoyant force is the weight of the liquid displaced by the cell, pointing upwards
+                let offset = rotate(self.bodies[b].cells[*cell].0, self.bodies[b].angle);
+                force.y -= liquid_mass * GRAVITY;
+                torque -= offset.x * liquid_mass * GRAVITY;
+                submerged += 1;
+            }
+        }
+        let body = &mut self.bodies[b];
+        body.velocity += force / mass.max(f32::EPSILON) * frame_time;
+        body.angular_velocity += torque / inertia * frame_time;
+        if submerged > 0 {
+            let drag =
+                (LIQUID_DRAG * frame_time * submerged as f32 / body.cells.len() as f32).min(1.0);
+            body.velocity *= 1.0 - drag;
+            body.angular_velocity *= 1.0 - drag;
+        }
+        body.angular_velocity *= ANGULAR_DAMPING;
+
+        // A body resting with its centre of mass beyond its supports tips over the edge of the support
+        let mut pivot: Option<Vec2> = None;
+        if let Some((left, right)) = self.support_range(b, &own) {
+            let body = &mut self.bodies[b];
+            let edge = if body.position.x < left.x - 0.5 {
+                Some(left)
+            } else if body.position.x > right.x + 0.5 {
+                Some(right)
+            } else {
+                None
+            };
+            if let Some(edge) = edge {
+                let arm = body.position.x - edge.x;
+                let pivot_inertia = inertia + mass * (body.position - edge).length_squared();
+                body.angular_velocity += mass * GRAVITY * arm / pivot_inertia * frame_time;
+                // The body rests on its support, so it only turns around the edge
+                body.velocity.y = body.velocity.y.min(0.0);
+                pivot = Some(edge);
+            }
+        }
+
+        let body = &self.bodies[b];
+        let steps = (body.velocity.x.abs())
+            .max(body.velocity.y.abs())
+            .max(body.angular_velocity.abs() * body.radius())
+            .ceil()
+            .clamp(1.0, MAX_SUBSTEPS as f32) as i32;
+        let mut impact: f32 = 0.0;
+        for _ in 0..steps {
+            let body = &self.bodies[b];
+            let step = body.velocity / steps as f32;
+            let turn = body.angular_velocity / steps as f32;
+            let turned_position = match pivot {
+                Some(pivot) => pivot + rotate(body.position - pivot, turn),
+                None => body.position,
+            };
+            let attempts = [
+                (turned_position + step, body.angle + turn, false, false),
+                (body.position + step, body.angle, false, true),
+                (body.position + vec2(step.x, 0.0), body.angle, true, true),
+                (body.position + vec2(0.0, step.y), body.angle, false, true),
+            ];
+            let mut moved = false;
+            for (attempt, (position, angle, blocked_y, stop_turning)) in attempts.iter().enumerate()
+            {
+                if let Some(occupied) = self.rasterize(b, *position, *angle) {
+                    let body = &mut self.bodies[b];
+                    if *stop_turning {
+                        body.angular_velocity = 0.0;
+                    }
+                    if *blocked_y {
+                        impact = impact.max(body.velocity.y.abs());
+                        body.velocity.y = 0.0;
+                    }
+                    if attempt == 3 {
+                        impact = impact.max(body.velocity.x.abs());
+                        body.velocity.x = 0.0;
+                    }
+                    body.position = *position;
+                    body.angle = *angle;
+                    self.place_body(b, occupied, rng);
+                    moved = true;
+                    break;
+                }
+            }
+            if !moved {
+                let body = &mut self.bodies[b];
+                impact = impact.max(body.velocity.length());
+                body.velocity = Vec2::ZERO;
+                body.angular_velocity = 0.0;
+                break;
+            }
+        }
+        impact
+    }
+    // Draws the body into its new cells, the liquids and gases it pushed out of the way are moved into the cells it left
+    fn place_body(&mut self, b: usize, occupied: Vec<(usize, usize)>, rng: &mut StdRng) {
+        let old: HashSet<usize> = self.bodies[b]
+            .occupied
+            .iter()
+            .map(|(index, _)| *index)
+            .collect();
+        let new: HashSet<usize> = occupied.iter().map(|(index, _)| *index).collect();
+        let mut displaced: Vec<Particle> = occupied
+            .iter()
+            .filter(|(index, _)| !old.contains(index))
+            .map(|(index, _)| self.contents[*index])
+            .filter(|particle| particle.0.phase != Phase::Void)
+            .collect();
+        let mut vacated: Vec<usize> = old.difference(&new).copied().collect();
+        vacated.sort_unstable();
+        // The chunks, which the body moved through, are woken, so the displaced particles flow
+        for index in old.symmetric_difference(&new) {
+            self.wake(*index);
+        }
+        for (index, cell) in &occupied {
+            self.contents[*index] = self.bodies[b].cells[*cell].1;
+        }
+        for index in vacated {
+            self.contents[index] = displaced.pop().unwrap_or(Particle(
+                materials::solid::VOID,
+                vec2(0.0, 0.0),
+                false,
+                rng.gen_range(0.0..1.0),
+                0.0,
+                AMBIENT_TEMPERATURE,
+            ));
+        }
+        // The body always leaves as many cells as it covers, so every displaced particle should have a cell, if one is
+        // left, it's put into an empty cell above the body, or into the nearest empty cell around it, if the column
+        // above it is full, or into any empty cell of the game_board. Only on a full game_board it's destroyed, which
+        // is recorded as a reaction
+        for particle in displaced {
+            let col_count = self.width as usize;
+            let start = occupied[0].0;
+            let (x, y) = self.position(start);
+            let is_free = |board: &Board, index: usize| {
+                board.contents[index].0.phase == Phase::Void && !new.contains(&index)
+            };
+            let around = (1..=MAX_REACH).flat_map(|distance| {
+                (-distance..=distance)
+                    .flat_map(move |dy| (-distance..=distance).map(move |dx| (dx, dy)))
+                    .filter(move |(dx, dy)| dx.abs().max(dy.abs()) == distance)
+            });
+            let free_cell = (start % col_count..start)
+                .step_by(col_count)
+                .find(|index| is_free(self, *index))
+                .or_else(|| {
+                    around
+                        .filter_map(|(dx, dy)| self.index(x + dx, y + dy))
+                        .find(|index| is_free(self, *index))
+                })
+                .or_else(|| (0..self.contents.len()).find(|index| is_free(self, *index)));
+            match free_cell {
+                Some(index) => {
+                    self.contents[index] = particle;
+                    self.wake(index);
+                }
+                None => self
+                    .reactions
+                    .push((particle.0.name, materials::solid::VOID.name)),
+            }
+        }
+        self.bodies[b].occupied = occupied;
+    }
+    // Breaks the weakest cells of the body off after an impact, the durability of every cell varies with its random
+    // number, so the body breaks along its weakest points
+    fn break_body(&mut self, b: usize, impact: f32) {
+        let stress = impact * IMPACT_STRESS;
+        let broken: Vec<usize> = self.bodies[b]
+            .occupied
+            .iter()
+            .filter(|(_, cell)| {
+                let particle = &self.bodies[b].cells[*cell].1;
+                (particle.0.durability as f32) * (0.5 + particle.3) < stress
+            })
+            .map(|(index, _)| *index)
+            .collect();
+        if !broken.is_empty() && broken.len() < self.bodies[b].occupied.len() {
+            self.break_cells(b, &broken);
+        }
+    }
+    // Replaces the body with new bodies made of its connected pieces, the broken cells become separate bodies
+    fn break_cells(&mut self, b: usize, broken: &[usize]) {
+        let body = self.bodies.remove(b);
+        // The pieces are found in the order of their cells, so the bodies are in the same order in every run
+        let remaining: BTreeSet<usize> = body
+            .occupied
+            .iter()
+            .filter(|(index, cell)| {
+                !broken.contains(index)
+                    && self.contents[*index].0.name == body.cells[*cell].1 .0.name
+            })
+            .map(|(index, _)| *index)
+            .collect();
+        let mut visited: HashSet<usize> = HashSet::new();
+        for start in &remaining {
+            if !visited.insert(*start) {
+                continue;
+            }
+            let mut group: Vec<usize> = vec![];
+            let mut stack: Vec<usize> = vec![*start];
+            while let Some(index) = stack.pop() {
+                group.push(index);
+                let (x, y) = self.position(index);
+                let neighbours = self.neighbours(x, y);
+                for neighbour in neighbours.filter_map(|(nx, ny)| self.index(nx, ny)) {
+                    if remaining.contains(&neighbour) && visited.insert(neighbour) {
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            let piece = self.create_body(&group, body.velocity, body.angular_velocity);
+            self.bodies.push(piece);
+        }
+        for index in broken {
+            let piece = self.create_body(&[*index], body.velocity, 0.0);
+            self.bodies.push(piece);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u16 = 40;
+    const HEIGHT: u16 = 40;
+    const WATER_TOP: i32 = 24;
+    const FRAME_TIME: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn a_wooden_plank_floats_partly_under_water() {
+        let mut game_board: Board = Board::new(WIDTH, HEIGHT);
+        for y in WATER_TOP..HEIGHT as i32 {
+            for x in 0..WIDTH as i32 {
+                game_board.paint_cell(x, y, materials::liquid::WATER);
+            }
+        }
+        for y in WATER_TOP - 4..WATER_TOP {
+            for x in 14..26 {
+                game_board.paint_cell(x, y, materials::solid::WOOD);
+            }
+        }
+        for _ in 0..600 {
+            game_board.step(FRAME_TIME, true);
+        }
+        let width: usize = WIDTH as usize;
+        let rows = |name: &str, cols: &dyn Fn(usize) -> bool| -> Vec<usize> {
+            (game_board.contents.iter().enumerate())
+                .filter(|(index, particle)| particle.0.name == name && cols(index % width))
+                .map(|(index, _)| index / width)
+                .collect()
+        };
+        let wood: Vec<usize> = rows(materials::solid::WOOD.name, &|_| true);
+        // The surface is measured away from the plank, the water on top of it runs off
+        let surface: usize = *rows(materials::liquid::WATER.name, &|x| !(10..30).contains(&x))
+            .iter()
+            .min()
+            .unwrap();
+        let (top, bottom) = (*wood.iter().min().unwrap(), *wood.iter().max().unwrap());
+        assert_eq!(wood.len(), 48);
+        assert!(
+            top < surface && bottom >= surface,
+            "{top}..={bottom}, {surface}"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use macroquad::prelude::*;
+
+use crate::boundaries::Boundaries;
+use crate::devices;
+use crate::forces::{ForceField, FIELD_BYTES};
+use crate::{materials, Board, Material, Particle};
+
+pub(crate) const SCENE_FILE: &str = "board.scene"; // File saved with Ctrl+S and loaded with Ctrl+O
+const MAGIC: &[u8; 7] = b"RRSCENE"; // Start of every scene file
+const VERSION: u8 = b'2'; // Version of the format, the byte after MAGIC, the older versions can't be read
+const CELL_BYTES: usize = 22; // Size of a saved cell (a u16 and 5 f32 numbers)
+
+pub(crate) fn save(game_board: &Board, path: &str) -> Result<(), String> {
+    std::fs::write(path, encode(game_board))
+        .map_err(|error| format!("Couldn't save \"{path}\": {error}"))
+}
+
+pub(crate) fn load(path: &str) -> Result<Board, String> {
+    let bytes: Vec<u8> =
+        std::fs::read(path).map_err(|error| format!("Couldn't read \"{path}\": {error}"))?;
+    decode(&bytes).map_err(|problem| format!("\"{path}\" isn't a valid scene file: {problem}"))
+}
+
+// The scene files keep the game_board exactly, so a saved game_board continues the same way after loading it:
+// the header (MAGIC, VERSION, width, height, tick, seed, modes of the edges, force fields), the used materials (name and
+// emission, the emitters with different emissions are different entries) and then every cell (index of its material,
+// velocity, random number, moisture and temperature), all numbers are little endian
+pub(crate) fn encode(game_board: &Board) -> Vec<u8> {
+    let mut used: Vec<Material> = vec![];
+    let entry = |used: &[Material], material: &Material| {
+        used.iter()
+            .position(|known| known.name == material.name && known.emits == material.emits)
+    };
+    for particle in &game_board.contents {
+        if entry(&used, &particle.0).is_none() {
+            used.push(particle.0);
+        }
+    }
+    let mut bytes: Vec<u8> = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&game_board.width.to_le_bytes());
+    bytes.extend_from_slice(&game_board.height.to_le_bytes());
+    bytes.extend_from_slice(&game_board.tick.to_le_bytes());
+    bytes.extend_from_slice(&game_board.seed.to_le_bytes());
+    bytes.extend_from_slice(&game_board.boundaries.to_bytes());
+    bytes.extend_from_slice(&(game_board.fields.len() as u16).to_le_bytes());
+    for field in &game_board.fields {
+        bytes.extend_from_slice(&field.to_bytes());
+    }
+    bytes.extend_from_slice(&(used.len() as u16).to_le_bytes());
+    for material in &used {
+        bytes.push(material.name.len() as u8);
+        bytes.extend_from_slice(material.name.as_bytes());
+        devices::encode_emission(&mut bytes, material.emits);
+    }
+    for particle in &game_board.contents {
+        let material = entry(&used, &particle.0);
+        bytes.extend_from_slice(&(material.unwrap_or(0) as u16).to_le_bytes());
+        for value in [
+            particle.1.x,
+            particle.1.y,
+            particle.3,
+            particle.4,
+            particle.5,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+// Reads a game_board written by encode, the error describes the problem of the bytes
+pub(crate) fn decode(bytes: &[u8]) -> Result<Board, String> {
+    let bytes: &[u8] = strip_header(bytes, MAGIC, VERSION)?;
+    let mut reader = Reader::new(bytes);
+    let read_error = || "the file ends too early".to_string();
+    let width = u16::from_le_bytes(reader.take().ok_or_else(read_error)?);
+    let height = u16::from_le_bytes(reader.take().ok_or_else(read_error)?);
+    let tick = u64::from_le_bytes(reader.take().ok_or_else(read_error)?);
+    let seed = u64::from_le_bytes(reader.take().ok_or_else(read_error)?);
+    let boundaries: Boundaries = Boundaries::from_bytes(reader.take().ok_or_else(read_error)?);
+    let field_count = u16::from_le_bytes(reader.take().ok_or_else(read_error)?);
+    let mut fields: Vec<ForceField> = vec![];
+    for _ in 0..field_count {
+        let bytes: [u8; FIELD_BYTES] = reader.take().ok_or_else(read_error)?;
+        fields.push(ForceField::from_bytes(bytes)?);
+    }
+    if width == 0 || height == 0 {
+        return Err("the game_board is empty".to_string());
+    }
+    let name_count = u16::from_le_bytes(reader.take().ok_or_else(read_error)?);
+    let mut names: Vec<Material> = vec![];
+    for _ in 0..name_count {
+        let [length] = reader.take().ok_or_else(read_error)?;
+        let name = reader.slice(length as usize).ok_or_else(read_error)?;
+        let name = String::from_utf8_lossy(name);
+        let mut material: Material =
+            materials::find(&name).ok_or_else(|| format!("unknown material \"{name}\""))?;
+        material.emits = devices::decode_emission(&mut reader)?;
+        names.push(material);
+    }
+    // The size is checked before the game_board is created, so a broken file can't allocate a huge game_board
+    if reader.remaining() != width as usize * height as usize * CELL_BYTES {
+        return Err("the number of the cells doesn't match the size".to_string());
+    }
+    let mut game_board: Board = Board::new(width, height);
+    for particle in game_board.contents.iter_mut() {
+        let material = u16::from_le_bytes(reader.take().ok_or_else(read_error)?);
+        let mut values = [0.0; 5];
+        for value in values.iter_mut() {
+            *value = f32::from_le_bytes(reader.take().ok_or_else(read_error)?);
+        }
+        let material = *names
+            .get(material as usize)
+            .ok_or_else(|| "unknown material index".to_string())?;
+        *particle = Particle(
+            material,
+            vec2(values[0], values[1]),
+            false,
+            values[2],
+            values[3],
+            values[4],
+        );
+    }
+    game_board.tick = tick;
+    game_board.seed = seed;
+    game_board.boundaries = boundaries;
+    game_board.fields = fields;
+    Ok(game_board)
+}
+
+// The bytes after the magic bytes and the version of a scene or replay file, the error tells a file of an older or
+// newer version apart from another file
+pub(crate) fn strip_header<'a>(
+    bytes: &'a [u8],
+    magic: &[u8],
+    version: u8,
+) -> Result<&'a [u8], String> {
+    let bytes: &[u8] = bytes
+        .strip_prefix(magic)
+        .ok_or_else(|| "unknown format".to_string())?;
+    match bytes.split_first() {
+        Some((&found, rest)) if found == version => Ok(rest),
+        Some((&found, _)) => Err(format!(
+            "version {} of the format isn't supported, only version {} can be read",
+            found as char, version as char
+        )),
+        None => Err("the file ends too early".to_string()),
+    }
+}
+
+// Reads the bytes of a scene or replay file in order
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize, // Index of the next unread byte
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+    pub(crate) fn slice(&mut self, length: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.position..self.position + length)?;
+        self.position += length;
+        Some(slice)
+    }
+    pub(crate) fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.slice(N)?.try_into().ok()
+    }
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+}
+
+// Ctrl+S saves the game_board into SCENE_FILE and Ctrl+O loads it, returns true, when the game_board was replaced, so
+// the size of the window can be changed
+pub(crate) fn handle_input(game_board: &mut Board) -> bool {
+    let ctrl: bool = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+    if ctrl && is_key_pressed(KeyCode::S) {
+        match save(game_board, SCENE_FILE) {
+            Ok(()) => println!("Saved the game_board into \"{SCENE_FILE}\""),
+            Err(error) => eprintln!("{error}"),
+        }
+    }
+    if ctrl && is_key_pressed(KeyCode::O) {
+        match load(SCENE_FILE) {
+            Ok(loaded) => {
+                *game_board = loaded;
+                return true;
+            }
+            Err(error) => eprintln!("{error}"),
+        }
+    }
+    false
+}
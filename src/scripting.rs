@@ -0,0 +1,393 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+
+use crate::forces::{FieldKind, ForceField};
+use crate::replay::Action;
+use crate::{materials, Board, Material, Particle, Phase};
+
+pub(crate) const SCRIPT_FILE: &str = "board.rhai"; // Script loaded with F10
+const MAX_OPERATIONS: u64 = 50_000_000; // Operations a script can run in one call, so an endless loop can't freeze the game
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// The state shared by the functions registered in the engine
+struct Shared {
+    board: Board,        // The game_board lent to the script during a call
+    touched: Vec<usize>, // Cells changed by the script during the call
+    rng: StdRng, // Random numbers of the script, they're separate from the simulation, so the replays stay the same
+}
+
+impl Shared {
+    // Index of the cell (x - column, y - row), scripts get an error for the cells outside of the game_board
+    fn index(&self, x: i64, y: i64) -> ScriptResult<usize> {
+        let (width, height) = (self.board.width as i64, self.board.height as i64);
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return Err(format!("The cell {x}, {y} is outside of the game_board").into());
+        }
+        Ok((y * width + x) as usize)
+    }
+    fn changed(&mut self, index: usize) {
+        self.board.wake(index);
+        self.touched.push(index);
+    }
+    // Puts a new particle of the material into the cell, like the brush does
+    fn place(&mut self, index: usize, material: Material) {
+        let random: f32 = self.board.contents[index].3;
+        self.board.contents[index] = Particle(
+            material,
+            material.phase.initial_velocity(),
+            true,
+            random,
+            0.0,
+            material.temperature,
+        );
+        self.changed(index);
+    }
+}
+
+// A Rhai script, which can read and change the game_board, it's called with these optional functions:
+// setup() - once after the script was loaded, e.g. to generate a scene
+// on_tick(tick) - after every simulated frame
+// update_<material>(x, y) - after every simulated frame for every particle of the material, e.g. update_sand(x, y),
+// so new rules of the materials can be tried out without recompiling. The name of the material is written in lower
+// case with an underscore instead of every other character than a letter or a digit, e.g. update_wet_sand(x, y)
+pub(crate) struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    shared: Rc<RefCell<Shared>>,
+    path: String,
+    rules: Vec<(&'static str, String)>, // Materials with an update function and the name of the function
+}
+
+impl Script {
+    // The random numbers of the script are seeded with the seed of the game_board, so the headless runs repeat
+    pub(crate) fn load(path: &str, seed: u64) -> Result<Script, String> {
+        let source: String = std::fs::read_to_string(path)
+            .map_err(|error| format!("Couldn't read \"{path}\": {error}"))?;
+        let shared: Rc<RefCell<Shared>> = Rc::new(RefCell::new(Shared {
+            board: Board::new(1, 1),
+            touched: vec![],
+            rng: StdRng::seed_from_u64(seed),
+        }));
+        let mut engine: Engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register_api(&mut engine, &shared);
+        let ast: AST = engine
+            .compile(source)
+            .map_err(|error| format!("Couldn't compile \"{path}\": {error}"))?;
+        let rules: Vec<(&'static str, String)> = all_materials()
+            .into_iter()
+            .filter_map(|material| {
+                let function: String = rule_name(material.name);
+                ast.iter_functions()
+                    .any(|defined| defined.name == function && defined.params.len() == 2)
+                    .then_some((material.name, function))
+            })
+            .collect();
+        Ok(Script {
+            engine,
+            ast,
+            scope: Scope::new(),
+            shared,
+            path: path.to_string(),
+            rules,
+        })
+    }
+    // Runs the top level statements of the script and its setup function
+    pub(crate) fn setup(&mut self, game_board: &mut Board) -> Result<(), String> {
+        self.with_board(game_board, |script| {
+            script
+                .engine
+                .run_ast_with_scope(&mut script.scope, &script.ast)?;
+            script.call_if_defined("setup", ())
+        })
+    }
+    // Calls the hooks of the script after a simulated frame
+    pub(crate) fn after_step(&mut self, game_board: &mut Board) -> Result<(), String> {
+        self.with_board(game_board, |script| {
+            let tick = script.shared.borrow().board.tick as i64;
+            script.call_if_defined("on_tick", (tick,))?;
+            for (name, function) in script.rules.clone() {
+                // The positions are collected first, so a particle moved by the script isn't updated twice
+                let cells: Vec<usize> = (script.shared.borrow().board.contents.iter())
+                    .enumerate()
+                    .filter(|(_, particle)| particle.0.name == name)
+                    .map(|(index, _)| index)
+                    .collect();
+                let width = script.shared.borrow().board.width as usize;
+                for index in cells {
+                    if script.shared.borrow().board.contents[index].0.name != name {
+                        continue;
+                    }
+                    let (x, y) = ((index % width) as i64, (index / width) as i64);
+                    let _: Dynamic =
+                        script
+                            .engine
+                            .call_fn(&mut script.scope, &script.ast, &function, (x, y))?;
+                }
+            }
+            Ok(())
+        })
+    }
+    fn call_if_defined(&mut self, name: &str, args: impl FuncArgs) -> ScriptResult<()> {
+        if self
+            .ast
+            .iter_functions()
+            .any(|defined| defined.name == name)
+        {
+            let _: Dynamic = self
+                .engine
+                .call_fn(&mut self.scope, &self.ast, name, args)?;
+        }
+        Ok(())
+    }
+    // Lends the game_board to the engine during the call, the changed cells are collected as an action, so they're
+    // recorded into the replays, the errors of the script are returned with its name
+    fn with_board(
+        &mut self,
+        game_board: &mut Board,
+        call: impl FnOnce(&mut Script) -> ScriptResult<()>,
+    ) -> Result<(), String> {
+        std::mem::swap(game_board, &mut self.shared.borrow_mut().board);
+        let result: ScriptResult<()> = call(self);
+        let mut shared = self.shared.borrow_mut();
+        std::mem::swap(game_board, &mut shared.board);
+        let mut touched: Vec<usize> = std::mem::take(&mut shared.touched);
+        touched.sort_unstable();
+        touched.dedup();
+        if !touched.is_empty() {
+            let cells = touched
+                .into_iter()
+                .map(|index| (index, game_board.contents[index]));
+            game_board.actions.push(Action::Cells(cells.collect()));
+        }
+        result.map_err(|error| format!("Error in \"{}\": {error}", self.path))
+    }
+}
+
+// Name of the update function of the material, it has to be a valid identifier of Rhai
+fn rule_name(material: &str) -> String {
+    let name: String = material
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("update_{name}")
+}
+
+fn all_materials() -> Vec<Material> {
+    let mut all: Vec<Material> = vec![materials::solid::VOID];
+    for material in materials::CATEGORIES
+        .iter()
+        .flat_map(|(_, materials)| materials.iter())
+    {
+        if !all.iter().any(|known| known.name == material.name) {
+            all.push(**material);
+        }
+    }
+    all
+}
+
+fn find_material(name: &str) -> ScriptResult<Material> {
+    materials::find(name).ok_or_else(|| format!("Unknown material \"{name}\"").into())
+}
+
+fn phase_name(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Void => "Void",
+        Phase::Solid => "Solid",
+        Phase::Powder { .. } => "Powder",
+        Phase::Liquid { .. } => "Liquid",
+        Phase::Gas { .. } => "Gas",
+        Phase::Plasma { .. } => "Plasma",
+    }
+}
+
+// The functions the scripts can call, they work on the lent game_board
+fn register_api(engine: &mut Engine, shared: &Rc<RefCell<Shared>>) {
+    let state = shared.clone();
+    engine.register_fn("width", move || state.borrow().board.width as i64);
+    let state = shared.clone();
+    engine.register_fn("height", move || state.borrow().board.height as i64);
+    let state = shared.clone();
+    engine.register_fn("tick", move || state.borrow().board.tick as i64);
+    // The name of the material in the cell, or an empty string outside of the game_board
+    let state = shared.clone();
+    engine.register_fn("get", move |x: i64, y: i64| {
+        let state = state.borrow();
+        state.index(x, y).map_or(String::new(), |index| {
+            state.board.contents[index].0.name.to_string()
+        })
+    });
+    let state = shared.clone();
+    engine.register_fn(
+        "set",
+        move |x: i64, y: i64, name: &str| -> ScriptResult<()> {
+            let mut state = state.borrow_mut();
+            let (index, material) = (state.index(x, y)?, find_material(name)?);
+            state.place(index, material);
+            Ok(())
+        },
+    );
+    // Fills the rectangle between the two corners with the material, the cells outside of the game_board are skipped
+    let state = shared.clone();
+    engine.register_fn(
+        "fill",
+        move |x1: i64, y1: i64, x2: i64, y2: i64, name: &str| -> ScriptResult<()> {
+            let material = find_material(name)?;
+            let mut state = state.borrow_mut();
+            for y in y1.min(y2)..=y1.max(y2) {
+                for x in x1.min(x2)..=x1.max(x2) {
+                    if let Ok(index) = state.index(x, y) {
+                        state.place(index, material);
+                    }
+                }
+            }
+            Ok(())
+        },
+    );
+    let state = shared.clone();
+    engine.register_fn(
+        "swap",
+        move |x1: i64, y1: i64, x2: i64, y2: i64| -> ScriptResult<()> {
+            let mut state = state.borrow_mut();
+            let (a, b) = (state.index(x1, y1)?, state.index(x2, y2)?);
+            state.board.contents.swap(a, b);
+            state.changed(a);
+            state.changed(b);
+            Ok(())
+        },
+    );
+    let state = shared.clone();
+    engine.register_fn("temperature", move |x: i64, y: i64| -> ScriptResult<f64> {
+        let state = state.borrow();
+        Ok(state.board.contents[state.index(x, y)?].5 as f64)
+    });
+    let state = shared.clone();
+    engine.register_fn(
+        "set_temperature",
+        move |x: i64, y: i64, temperature: f64| -> ScriptResult<()> {
+            let mut state = state.borrow_mut();
+            let index = state.index(x, y)?;
+            state.board.contents[index].5 = temperature as f32;
+            state.changed(index);
+            Ok(())
+        },
+    );
+    let state = shared.clone();
+    engine.register_fn("moisture", move |x: i64, y: i64| -> ScriptResult<f64> {
+        let state = state.borrow();
+        Ok(state.board.contents[state.index(x, y)?].4 as f64)
+    });
+    let state = shared.clone();
+    engine.register_fn(
+        "set_moisture",
+        move |x: i64, y: i64, moisture: f64| -> ScriptResult<()> {
+            let mut state = state.borrow_mut();
+            let index = state.index(x, y)?;
+            state.board.contents[index].4 = moisture.max(0.0) as f32;
+            state.changed(index);
+            Ok(())
+        },
+    );
+    // The properties of a material, or () if there's no material with the name
+    engine.register_fn("material", |name: &str| -> Dynamic {
+        let Some(material) = materials::find(name) else {
+            return Dynamic::UNIT;
+        };
+        let mut properties = Map::new();
+        properties.insert("name".into(), material.name.into());
+        properties.insert("phase".into(), phase_name(material.phase).into());
+        properties.insert("mass".into(), (material.mass as f64).into());
+        properties.insert("durability".into(), (material.durability as i64).into());
+        properties.insert("flammability".into(), (material.flammability as f64).into());
+        properties.insert("temperature".into(), (material.temperature as f64).into());
+        properties.insert("absorbency".into(), (material.absorbency as f64).into());
+        properties.insert("emission".into(), (material.emission as f64).into());
+        properties.into()
+    });
+    engine.register_fn("materials", || -> Array {
+        all_materials()
+            .into_iter()
+            .map(|material| material.name.into())
+            .collect()
+    });
+    // Places a force field ("wind up", "wind right", "wind down", "wind left", "vortex", "attractor" or "repeller")
+    // centred on the cell
+    let state = shared.clone();
+    engine.register_fn(
+        "add_field",
+        move |kind: &str, x: i64, y: i64, radius: i64, strength: f64| -> ScriptResult<()> {
+            let kind: FieldKind = FieldKind::parse(kind)?;
+            let mut state = state.borrow_mut();
+            state.index(x, y)?;
+            state.board.add_field(ForceField {
+                kind,
+                x: x as i32,
+                y: y as i32,
+                radius: radius.max(0) as i32,
+                strength: strength as f32,
+            });
+            Ok(())
+        },
+    );
+    // Random number between 0 and 1
+    let state = shared.clone();
+    engine.register_fn("random", move || state.borrow_mut().rng.gen::<f64>());
+}
+
+// The script loaded with the F10 key, its hooks run after every simulated frame, Shift+F10 unloads it
+#[derive(Default)]
+pub(crate) struct Scripting {
+    script: Option<Script>,
+    message: String, // The loaded script or its last error
+}
+
+impl Scripting {
+    pub(crate) fn handle_input(&mut self, game_board: &mut Board) {
+        if !is_key_pressed(KeyCode::F10) {
+            return;
+        }
+        if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+            self.script = None;
+            self.message = String::new();
+            return;
+        }
+        let result = Script::load(SCRIPT_FILE, game_board.seed).and_then(|mut script| {
+            script.setup(game_board)?;
+            Ok(script)
+        });
+        match result {
+            Ok(script) => {
+                self.message = format!("Running \"{SCRIPT_FILE}\" (Shift+F10 stops it)");
+                self.script = Some(script);
+            }
+            Err(error) => self.message = error,
+        }
+    }
+    // Called after every simulated frame, the script is stopped after an error
+    pub(crate) fn after_step(&mut self, game_board: &mut Board) {
+        let Some(script) = &mut self.script else {
+            return;
+        };
+        if let Err(error) = script.after_step(game_board) {
+            eprintln!("{error}");
+            self.message = error;
+            self.script = None;
+        }
+    }
+    pub(crate) fn draw(&self, x: f32, y: f32) {
+        draw_text(&self.message, x, y, 20.0, WHITE);
+    }
+}
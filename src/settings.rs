@@ -0,0 +1,187 @@
+use macroquad::prelude::*;
+
+use crate::boundaries::Boundaries;
+use crate::{draw_button, BUTTON_HEIGHT, BUTTON_SPACING, BUTTON_WIDTH};
+
+const MIN_BOARD_SIZE: u16 = 10;
+const MAX_BOARD_SIZE: u16 = 2000;
+const MAX_CELLSIZE: u32 = 16;
+const BOARD_SIZE_STEP: u16 = 10; // Change of the width/height of the game_board per click in the settings panel
+
+// Size of the game_board and its cells and the modes of its edges, can be given as command line options and changed
+// in the settings panel
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct Settings {
+    pub(crate) width: u16,             // Number of columns of the game_board
+    pub(crate) height: u16,            // Number of rows of the game_board
+    pub(crate) cell_size: u32,         // Size of a cell on the screen (in pixels)
+    pub(crate) boundaries: Boundaries, // Modes of the edges of the game_board
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: 300,
+            height: 200,
+            cell_size: 3,
+            boundaries: Boundaries::default(),
+        }
+    }
+}
+
+impl Settings {
+    // Reads the "--width", "--height", "--cell-size" and "--edges" options, the missing ones keep their default value
+    pub(crate) fn from_args(args: impl Iterator<Item = String>) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        let mut args = Args::new(args);
+        while let Some(option) = args.next()? {
+            match option.as_str() {
+                "--width" => settings.width = parse_board_size(&args.value()?)?,
+                "--height" => settings.height = parse_board_size(&args.value()?)?,
+                "--cell-size" => {
+                    let cell_size: u32 = args.number()?;
+                    if !(1..=MAX_CELLSIZE).contains(&cell_size) {
+                        return Err(format!(
+                            "The cell size must be between 1 and {MAX_CELLSIZE}"
+                        ));
+                    }
+                    settings.cell_size = cell_size;
+                }
+                "--edges" => settings.boundaries = Boundaries::parse(&args.value()?)?,
+                _ => return Err(format!("Unknown option \"{option}\"")),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+// The command line arguments of the window and of the commands, the value of an option follows it ("--ticks 100") or
+// is joined to it with an equals sign ("--ticks=100")
+pub(crate) struct Args<I: Iterator<Item = String>> {
+    args: I,
+    option: String,               // The last option, it's named in the errors
+    inline_value: Option<String>, // Value joined to the last option, it has to be read before the next argument
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    pub(crate) fn new(args: I) -> Args<I> {
+        Args {
+            args,
+            option: String::new(),
+            inline_value: None,
+        }
+    }
+    // The next option or other argument (e.g. a file), the arguments, which aren't options, are never split
+    pub(crate) fn next(&mut self) -> Result<Option<String>, String> {
+        if self.inline_value.take().is_some() {
+            return Err(format!("\"{}\" doesn't take a value", self.option));
+        }
+        let Some(arg) = self.args.next() else {
+            return Ok(None);
+        };
+        if !arg.starts_with("--") {
+            return Ok(Some(arg));
+        }
+        let option: String = match arg.split_once('=') {
+            Some((option, value)) => {
+                self.inline_value = Some(value.to_string());
+                option.to_string()
+            }
+            None => arg,
+        };
+        self.option = option.clone();
+        Ok(Some(option))
+    }
+    // The value of the last option
+    pub(crate) fn value(&mut self) -> Result<String, String> {
+        self.inline_value
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or(format!("Missing value for \"{}\"", self.option))
+    }
+    pub(crate) fn number<T: std::str::FromStr>(&mut self) -> Result<T, String> {
+        let value: String = self.value()?;
+        value.parse().map_err(|_| {
+            format!(
+                "The value of \"{}\" must be a number, not \"{value}\"",
+                self.option
+            )
+        })
+    }
+}
+
+pub(crate) fn parse_board_size(value: &str) -> Result<u16, String> {
+    let size: u16 = value
+        .parse()
+        .map_err(|_| format!("The board size must be a number, not \"{value}\""))?;
+    if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&size) {
+        return Err(format!(
+            "The board size must be between {MIN_BOARD_SIZE} and {MAX_BOARD_SIZE}"
+        ));
+    }
+    Ok(size)
+}
+
+// Draws the editable copy of the settings, returns true, when the "Apply" button is pressed
+pub(crate) fn draw_settings_panel(new_settings: &mut Settings, x: f32, y: f32) -> bool {
+    let small_button: f32 = BUTTON_HEIGHT;
+    // The edges are labelled with their first letters (top, right, bottom, left), so the rows fit next to the buttons
+    let edges = new_settings.boundaries.to_array();
+    let rows: [(&str, String); 7] = [
+        ("W", new_settings.width.to_string()),
+        ("H", new_settings.height.to_string()),
+        ("Cell", new_settings.cell_size.to_string()),
+        ("T", edges[0].name().to_string()),
+        ("R", edges[1].name().to_string()),
+        ("B", edges[2].name().to_string()),
+        ("L", edges[3].name().to_string()),
+    ];
+    for (row, (label, value)) in rows.iter().enumerate() {
+        let row_y = y + BUTTON_SPACING * row as f32;
+        draw_text(&format!("{label}: {value}"), x, row_y + 20.0, 20.0, WHITE);
+        let minus = draw_button(
+            "-",
+            x + BUTTON_WIDTH,
+            row_y,
+            small_button,
+            small_button,
+            DARKGRAY,
+        );
+        let plus = draw_button(
+            "+",
+            x + BUTTON_WIDTH + small_button + 5.0,
+            row_y,
+            small_button,
+            small_button,
+            DARKGRAY,
+        );
+        let change: i32 = plus as i32 - minus as i32;
+        match row {
+            0 => new_settings.width = step_board_size(new_settings.width, change),
+            1 => new_settings.height = step_board_size(new_settings.height, change),
+            2 => {
+                new_settings.cell_size =
+                    (new_settings.cell_size as i32 + change).clamp(1, MAX_CELLSIZE as i32) as u32
+            }
+            // The edge modes are cycled through with both buttons
+            edge => {
+                let mut modes = new_settings.boundaries.to_array();
+                modes[edge - 3] = modes[edge - 3].cycle(change);
+                new_settings.boundaries = Boundaries::from_array(modes);
+            }
+        }
+    }
+    draw_button(
+        "Apply",
+        x,
+        y + BUTTON_SPACING * rows.len() as f32,
+        BUTTON_WIDTH,
+        BUTTON_HEIGHT,
+        DARKBLUE,
+    )
+}
+
+fn step_board_size(size: u16, change: i32) -> u16 {
+    (size as i32 + change * BOARD_SIZE_STEP as i32)
+        .clamp(MIN_BOARD_SIZE as i32, MAX_BOARD_SIZE as i32) as u16
+}
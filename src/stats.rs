@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+
+use macroquad::prelude::*;
+
+use crate::camera::BoardCamera;
+use crate::{materials, Board};
+
+const MAX_SAMPLES: usize = 240; // Number of the samples shown in the graphs
+const SAMPLE_INTERVAL: f32 = 0.25; // Time between two samples of the stats panel (in seconds)
+const PANEL_WIDTH: f32 = 260.0;
+const GRAPH_HEIGHT: f32 = 50.0;
+
+type Series = (Color, Vec<f32>); // Colour of a line of a graph and its values
+
+// The state of the game_board at one tick, the void isn't counted
+#[derive(Clone, Default)]
+pub(crate) struct Sample {
+    pub(crate) tick: u64,
+    pub(crate) ticks_per_second: f32,
+    pub(crate) materials: BTreeMap<&'static str, (i64, f32)>, // Number of the particles and their average temperature
+    pub(crate) moving: i64,  // Particles moved by the solvers in the last tick
+    pub(crate) settled: i64, // Particles, which stayed in their cells
+    pub(crate) temperature: f32, // Average temperature of all particles (in °C)
+}
+
+impl Sample {
+    // The moved particles are counted by the solvers, every swap moves a particle, so the rest of them settled
+    pub(crate) fn new(game_board: &Board, ticks_per_second: f32) -> Sample {
+        let mut sample = Sample {
+            tick: game_board.tick,
+            ticks_per_second,
+            ..Default::default()
+        };
+        let mut temperature_sum: f32 = 0.0;
+        let mut particles: i64 = 0;
+        for particle in &game_board.contents {
+            if particle.0.name == materials::solid::VOID.name {
+                continue;
+            }
+            let entry = sample.materials.entry(particle.0.name).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += particle.5;
+            temperature_sum += particle.5;
+            particles += 1;
+        }
+        sample.moving = (game_board.moves as i64).min(particles);
+        sample.settled = particles - sample.moving;
+        for (count, temperature) in sample.materials.values_mut() {
+            *temperature /= *count as f32;
+        }
+        if particles > 0 {
+            sample.temperature = temperature_sum / particles as f32;
+        }
+        sample
+    }
+    pub(crate) fn count(&self, name: &str) -> i64 {
+        self.materials.get(name).map_or(0, |(count, _)| *count)
+    }
+}
+
+// Names of the materials, which get a column in the CSV files, every material of the palette
+fn csv_materials() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = vec![];
+    for material in materials::CATEGORIES
+        .iter()
+        .flat_map(|(_, materials)| materials.iter())
+    {
+        if !names.contains(&material.name) {
+            names.push(material.name);
+        }
+    }
+    names
+}
+
+// Writes the samples of a headless run as CSV rows, one row per sample with a column for every material
+pub(crate) struct CsvWriter {
+    file: std::io::BufWriter<std::fs::File>,
+    path: String,
+    materials: Vec<&'static str>,
+}
+
+impl CsvWriter {
+    pub(crate) fn create(path: &str) -> Result<CsvWriter, String> {
+        let file = std::fs::File::create(path)
+            .map_err(|error| format!("Couldn't create \"{path}\": {error}"))?;
+        let mut writer = CsvWriter {
+            file: std::io::BufWriter::new(file),
+            path: path.to_string(),
+            materials: csv_materials(),
+        };
+        let header: String = [
+            "tick",
+            "ticks_per_second",
+            "moving",
+            "settled",
+            "temperature",
+        ]
+        .into_iter()
+        .chain(writer.materials.iter().copied())
+        .collect::<Vec<&str>>()
+        .join(",");
+        writer.write_line(&header)?;
+        Ok(writer)
+    }
+    pub(crate) fn write(&mut self, sample: &Sample) -> Result<(), String> {
+        let mut columns: Vec<String> = vec![
+            sample.tick.to_string(),
+            format!("{:.1}", sample.ticks_per_second),
+            sample.moving.to_string(),
+            sample.settled.to_string(),
+            format!("{:.2}", sample.temperature),
+        ];
+        columns.extend(
+            self.materials
+                .iter()
+                .map(|name| sample.count(name).to_string()),
+        );
+        self.write_line(&columns.join(","))
+    }
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.file, "{line}")
+            .map_err(|error| format!("Couldn't write \"{}\": {error}", self.path))
+    }
+}
+
+// The stats panel toggled with the F7 key, it shows the state of the game_board and graphs of the last samples
+#[derive(Default)]
+pub(crate) struct Stats {
+    pub(crate) enabled: bool,
+    samples: VecDeque<Sample>,
+    since_sample: f32, // Time passed since the last sample (in seconds)
+    last_tick: u64,    // Tick of the game_board at the last sample
+}
+
+impl Stats {
+    pub(crate) fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::F7) {
+            self.enabled = !self.enabled;
+            self.samples.clear();
+        }
+    }
+    // Samples the game_board every SAMPLE_INTERVAL seconds, the ticks per second are measured between the samples
+    pub(crate) fn update(&mut self, game_board: &Board, frame_time: f32) {
+        if !self.enabled {
+            return;
+        }
+        self.since_sample += frame_time;
+        if self.since_sample < SAMPLE_INTERVAL && !self.samples.is_empty() {
+            return;
+        }
+        // A cleared or loaded game_board can have an older tick
+        let ticks: u64 = game_board.tick.saturating_sub(self.last_tick);
+        let ticks_per_second: f32 = ticks as f32 / self.since_sample.max(f32::EPSILON);
+        self.samples
+            .push_back(Sample::new(game_board, ticks_per_second));
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.since_sample = 0.0;
+        self.last_tick = game_board.tick;
+    }
+    pub(crate) fn draw(&self, camera: &BoardCamera) {
+        let Some(sample) = self.samples.back().filter(|_| self.enabled) else {
+            return;
+        };
+        let (x, y): (f32, f32) = (camera.viewport.x + 5.0, camera.viewport.y + 5.0);
+        let mut lines: Vec<(String, Color)> = vec![
+            (
+                format!("Ticks per second: {:.0}", sample.ticks_per_second),
+                WHITE,
+            ),
+            (
+                format!("Moving: {}  Settled: {}", sample.moving, sample.settled),
+                WHITE,
+            ),
+            (format!("Temperature: {:.1} °C", sample.temperature), WHITE),
+        ];
+        for (name, (count, temperature)) in &sample.materials {
+            lines.push((
+                format!("{name}: {count} ({temperature:.0} °C)"),
+                material_color(name),
+            ));
+        }
+        let graphs_y: f32 = y + lines.len() as f32 * 18.0 + 10.0;
+        let height: f32 = graphs_y - y + (GRAPH_HEIGHT + 22.0) * 3.0;
+        draw_rectangle(x, y, PANEL_WIDTH, height, Color::new(0.0, 0.0, 0.0, 0.75));
+        for (row, (line, color)) in lines.iter().enumerate() {
+            draw_text(line, x + 5.0, y + 18.0 * (row + 1) as f32, 18.0, *color);
+        }
+        // The populations of all materials share one graph, so they can be compared
+        let populations: Vec<Series> = sample
+            .materials
+            .keys()
+            .map(|name| {
+                let values = self.samples.iter().map(|sample| sample.count(name) as f32);
+                (material_color(name), values.collect())
+            })
+            .collect();
+        let moving: Vec<f32> = self
+            .samples
+            .iter()
+            .map(|sample| sample.moving as f32)
+            .collect();
+        let temperature: Vec<f32> = self
+            .samples
+            .iter()
+            .map(|sample| sample.temperature)
+            .collect();
+        let graphs: [(&str, Vec<Series>); 3] = [
+            ("Population", populations),
+            ("Moving", vec![(SKYBLUE, moving)]),
+            ("Temperature", vec![(ORANGE, temperature)]),
+        ];
+        for (number, (title, series)) in graphs.iter().enumerate() {
+            let graph_y: f32 = graphs_y + number as f32 * (GRAPH_HEIGHT + 22.0);
+            draw_text(title, x + 5.0, graph_y + 14.0, 16.0, LIGHTGRAY);
+            draw_graph(
+                series,
+                x + 5.0,
+                graph_y + 18.0,
+                PANEL_WIDTH - 10.0,
+                GRAPH_HEIGHT,
+            );
+        }
+    }
+}
+
+fn material_color(name: &str) -> Color {
+    materials::find(name).map_or(WHITE, |material| Color {
+        a: 1.0,
+        ..material.color
+    })
+}
+
+// Draws the series as lines over the last MAX_SAMPLES samples, all series share the same scale, the maximum value
+// is written next to the graph
+fn draw_graph(series: &[Series], x: f32, y: f32, width: f32, height: f32) {
+    draw_rectangle_lines(x, y, width, height, 1.0, DARKGRAY);
+    let (min, max): (f32, f32) = series
+        .iter()
+        .flat_map(|(_, values)| values.iter().copied())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+    if !min.is_finite() {
+        return;
+    }
+    let (min, max): (f32, f32) = (min.min(0.0), max.max(min.min(0.0) + 1.0));
+    draw_text(&format!("{max:.0}"), x + width - 40.0, y - 4.0, 14.0, GRAY);
+    let step: f32 = width / (MAX_SAMPLES - 1) as f32;
+    for (color, values) in series {
+        let point = |index: usize, value: f32| {
+            vec2(
+                x + index as f32 * step,
+                y + height - (value - min) / (max - min) * height,
+            )
+        };
+        for (index, pair) in values.windows(2).enumerate() {
+            let (start, end) = (point(index, pair[0]), point(index + 1, pair[1]));
+            draw_line(start.x, start.y, end.x, end.y, 1.0, *color);
+        }
+    }
+}